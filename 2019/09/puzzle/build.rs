@@ -0,0 +1,20 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+/// Compiles the puzzle input ahead of time into `$OUT_DIR/boost.rs`, and
+/// day 05's into `$OUT_DIR/diagnostics.rs` for the tests to compare against
+/// the interpreter.
+fn main() {
+    compile("../input", "09/input", "boost.rs");
+    compile("../../05/input", "05/input", "diagnostics.rs");
+}
+
+fn compile(path: &str, name: &str, module: &str) {
+    println!("cargo:rerun-if-changed={}", path);
+    let program = intcode::read_program(BufReader::new(File::open(path).unwrap())).unwrap();
+    let source = intcode::aot::compile(&program, name);
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join(module);
+    fs::write(out, source).unwrap();
+}
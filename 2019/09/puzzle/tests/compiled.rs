@@ -0,0 +1,79 @@
+//! The compiled puzzle inputs against the interpreter: outputs, the final
+//! state and where either stops have to agree.
+
+use intcode::aot::Compiled;
+use intcode::{IntcodeError, Machine, Output};
+
+mod boost {
+    include!(concat!(env!("OUT_DIR"), "/boost.rs"));
+}
+
+mod diagnostics {
+    include!(concat!(env!("OUT_DIR"), "/diagnostics.rs"));
+}
+
+const DAY_05: &str = include_str!("../../../05/input");
+const DAY_09: &str = include_str!("../../input");
+
+/// Outputs up to the first stop, and the stop.
+fn outputs<F>(mut run: F) -> (Vec<i64>, Result<Output, IntcodeError>)
+where
+    F: FnMut() -> Result<Output, IntcodeError>,
+{
+    let mut outputs = vec![];
+    loop {
+        match run() {
+            Ok(Output::Value(v)) => outputs.push(v),
+            stop => return (outputs, stop),
+        }
+    }
+}
+
+fn compare(load: fn(Machine) -> Compiled, source: &str, input: &[i64]) {
+    let program = intcode::read_program(source.as_bytes()).unwrap();
+    let mut machine = Machine::new(&program);
+    machine.input.extend(input);
+    let mut compiled = load(machine.clone());
+    assert_eq!(
+        outputs(|| compiled.run()),
+        outputs(|| machine.run()),
+        "input {:?}",
+        input
+    );
+    let compiled = compiled.machine();
+    assert_eq!((compiled.ip, compiled.rb), (machine.ip, machine.rb));
+    for address in 0..program.len() + 1024 {
+        assert_eq!(
+            compiled.get(address),
+            machine.get(address),
+            "at {}",
+            address
+        );
+    }
+}
+
+#[test]
+fn day05_matches_the_interpreter() {
+    compare(diagnostics::load, DAY_05, &[1]);
+    compare(diagnostics::load, DAY_05, &[5]);
+    compare(diagnostics::load, DAY_05, &[8]);
+    compare(diagnostics::load, DAY_05, &[]);
+}
+
+#[test]
+fn day09_matches_the_interpreter() {
+    compare(boost::load, DAY_09, &[1]);
+    compare(boost::load, DAY_09, &[2]);
+    compare(boost::load, DAY_09, &[3]);
+    compare(boost::load, DAY_09, &[]);
+}
+
+#[test]
+fn compiled_answers() {
+    let program = intcode::read_program(DAY_09.as_bytes()).unwrap();
+    let mut compiled = boost::load(Machine::with_input(&program, 2));
+    assert_eq!(compiled.run(), Ok(Output::Value(73144)));
+    let program = intcode::read_program(DAY_05.as_bytes()).unwrap();
+    let mut compiled = diagnostics::load(Machine::with_input(&program, 5));
+    assert_eq!(compiled.run(), Ok(Output::Value(10428568)));
+}
@@ -1,145 +1,23 @@
+use intcode::ascii::AsciiMachine;
+use intcode::{IntcodeError, Machine};
 use std::collections::{BTreeMap, BTreeSet};
 use std::io;
-use std::io::prelude::*;
 use std::{thread, time};
 
-#[derive(Debug, PartialEq)]
-enum Output {
-    Halt(i64),
-    NeedsInput,
-    Value(i64),
-}
-
-#[derive(Debug)]
-struct State {
-    memory: BTreeMap<usize, i64>,
-    input: Option<i64>,
-    ip: usize,
-    rb: usize,
-}
-
-impl State {
-    fn new(memory: &Vec<i64>) -> State {
-        let mut s = State {
-            memory: BTreeMap::new(),
-            input: None,
-            ip: 0,
-            rb: 0,
-        };
-        for (i, v) in memory.iter().enumerate() {
-            s.memory.insert(i, *v);
-        }
-        s
-    }
-
-    fn get_operand(&self, mode: i64, i: usize) -> i64 {
-        match mode {
-            1 => self.get(i),
-            _ => self.get(self.get_address(mode, i)),
-        }
-    }
-
-    fn get_address(&self, mode: i64, i: usize) -> usize {
-        let immediate = self.get(i);
-        let address = match mode {
-            0 => immediate,
-            2 => immediate + self.rb as i64,
-            _ => panic!(),
-        };
-        assert!(address >= 0);
-        address as usize
-    }
-
-    fn get(&self, i: usize) -> i64 {
-        match self.memory.get(&i) {
-            Some(v) => *v,
-            None => 0,
-        }
-    }
-
-    fn set(&mut self, i: usize, v: i64) {
-        *self.memory.entry(i).or_insert(0) = v;
-    }
-
-    fn run(&mut self) -> Output {
-        loop {
-            let opcode = self.get(self.ip);
-            match (opcode / 100, opcode % 100) {
-                (0, 99) => return Output::Halt(self.get(0)),
-                (mode, op) => {
-                    let modes: [i64; 3] = [mode % 10, mode % 100 / 10, mode / 100];
-                    match op {
-                        1 | 2 => {
-                            let op1 = self.get_operand(modes[0], self.ip + 1);
-                            let op2 = self.get_operand(modes[1], self.ip + 2);
-                            let to = self.get_address(modes[2], self.ip + 3);
-                            if op == 1 {
-                                self.set(to, op1 + op2);
-                            } else {
-                                self.set(to, op1 * op2);
-                            }
-                            self.ip += 4;
-                        }
-                        3 => {
-                            if self.input.is_none() {
-                                return Output::NeedsInput;
-                            }
-                            let to = self.get_address(modes[0], self.ip + 1);
-                            self.set(to, self.input.unwrap());
-                            self.input = None;
-                            self.ip += 2;
-                        }
-                        4 => {
-                            let to = self.get_operand(modes[0], self.ip + 1);
-                            self.ip += 2;
-                            return Output::Value(to);
-                        }
-                        5 | 6 => {
-                            let cond = self.get_operand(modes[0], self.ip + 1);
-                            let destination = self.get_operand(modes[1], self.ip + 2);
-                            if (op == 5 && cond != 0) || (op == 6 && cond == 0) {
-                                self.ip = destination as usize;
-                            } else {
-                                self.ip += 3;
-                            }
-                        }
-                        7 | 8 => {
-                            let op1 = self.get_operand(modes[0], self.ip + 1);
-                            let op2 = self.get_operand(modes[1], self.ip + 2);
-                            let to = self.get_address(modes[2], self.ip + 3);
-                            if (op == 7 && op1 < op2) || (op == 8 && op1 == op2) {
-                                self.set(to, 1);
-                            } else {
-                                self.set(to, 0);
-                            }
-                            self.ip += 4;
-                        }
-                        9 => {
-                            let op1 = self.get_operand(modes[0], self.ip + 1);
-                            self.rb += op1 as usize;
-                            self.ip += 2;
-                        }
-                        op => panic!(dbg!(op)),
-                    };
-                }
-            };
-        }
-    }
-}
-
+#[allow(dead_code)]
 fn print_map(map: &BTreeMap<(i64, i64), char>, lower_left: (i64, i64), upper_right: (i64, i64)) {
     for j in lower_left.0 - 4..upper_right.0 + 2 {
         eprint!("{}", j.abs() / 10);
     }
-    eprintln!("");
+    eprintln!();
     for j in lower_left.0 - 4..upper_right.0 + 2 {
         eprint!("{}", j.abs() % 10);
     }
-    eprintln!("");
+    eprintln!();
     for _ in lower_left.0 - 4..upper_right.0 + 2 {
         eprint!("=");
     }
-    eprintln!("");
+    eprintln!();
     for i in (lower_left.1..upper_right.1 + 1).rev() {
         eprint!("{:3}|", i);
         for j in lower_left.0..upper_right.0 + 1 {
@@ -149,12 +27,12 @@ fn print_map(map: &BTreeMap<(i64, i64), char>, lower_left: (i64, i64), upper_rig
             }
         }
         eprint!("|");
-        eprintln!("");
+        eprintln!();
     }
     for _ in lower_left.0 - 4..upper_right.0 + 2 {
         eprint!("=");
     }
-    eprintln!("");
+    eprintln!();
     thread::sleep(time::Duration::from_millis(100));
 }
 
@@ -183,21 +61,21 @@ fn split_commands(
     mut sequences: Vec<String>,
     mut letters: Vec<String>,
 ) -> Option<(String, Vec<String>)> {
-    let start = commands.find(|c: char| c == 'L' || c == 'R');
+    let start = commands.find(['L', 'R']);
     match start {
         None => {
-            if letters.len() == 0 {
+            if letters.is_empty() {
                 if commands.len() > 20 {
-                    return None;
+                    None
                 } else {
-                    return Some((commands.to_string(), sequences));
+                    Some((commands.to_string(), sequences))
                 }
             } else {
-                return None;
+                None
             }
         }
         Some(b) => {
-            if letters.len() == 0 {
+            if letters.is_empty() {
                 return None;
             }
             let letter = letters.remove(0);
@@ -206,14 +84,12 @@ fn split_commands(
                 if last {
                     break;
                 }
-                let mut sequence;
+                let sequence;
                 if e < commands.len() {
-                    if commands.as_bytes()[e] == 'A' as u8 || commands.as_bytes()[e] == 'B' as u8 {
+                    if commands.as_bytes()[e] == b'A' || commands.as_bytes()[e] == b'B' {
                         last = true;
                         sequence = &commands[b..e - 1];
-                    } else if commands.as_bytes()[e] == 'L' as u8
-                        || commands.as_bytes()[e] == 'R' as u8
-                    {
+                    } else if commands.as_bytes()[e] == b'L' || commands.as_bytes()[e] == b'R' {
                         sequence = &commands[b..e - 1];
                     } else {
                         continue;
@@ -241,57 +117,34 @@ fn split_commands(
     }
 }
 
-fn main() {
-    let memory: Vec<i64> = io::stdin()
-        .lock()
-        .lines()
-        .map(|line| {
-            line.unwrap()
-                .split(',')
-                .map(|s| s.parse().unwrap())
-                .collect::<Vec<i64>>()
-        })
-        .flatten()
-        .collect();
+fn main() -> Result<(), IntcodeError> {
+    let memory = intcode::read_program(io::stdin().lock()).unwrap();
 
-    let mut camera = State::new(&memory);
-    let mut pos = (0, 0);
+    let mut camera = AsciiMachine::new(&memory);
     let mut map: BTreeMap<(i64, i64), char> = BTreeMap::new();
     let mut lower_left = (0, 0);
     let mut upper_right = (0, 0);
     let mut robot = None;
-    loop {
-        match camera.run() {
-            Output::Value(v) => {
-                pos = match v {
-                    10 => (0, pos.1 - 1),
-                    ch => {
-                        let ch = ch as u8 as char;
-                        match ch {
-                            '^' | 'v' | '<' | '>' => robot = Some(pos),
-                            _ => {}
-                        };
-                        lower_left.0 = std::cmp::min(lower_left.0, pos.0);
-                        lower_left.1 = std::cmp::min(lower_left.1, pos.1);
-                        upper_right.0 = std::cmp::max(upper_right.0, pos.0);
-                        upper_right.1 = std::cmp::max(upper_right.1, pos.1);
-                        map.insert(pos, ch);
-                        (pos.0 + 1, pos.1)
-                    }
-                };
-            }
-            Output::Halt(v) => {
-                println!("Halt {}", v);
-                break;
-            }
-            Output::NeedsInput => panic!(),
+    for (y, line) in camera.run()?.lines.iter().enumerate() {
+        for (x, ch) in line.chars().enumerate() {
+            let pos = (x as i64, -(y as i64));
+            match ch {
+                '^' | 'v' | '<' | '>' => robot = Some(pos),
+                _ => {}
+            };
+            lower_left.0 = std::cmp::min(lower_left.0, pos.0);
+            lower_left.1 = std::cmp::min(lower_left.1, pos.1);
+            upper_right.0 = std::cmp::max(upper_right.0, pos.0);
+            upper_right.1 = std::cmp::max(upper_right.1, pos.1);
+            map.insert(pos, ch);
         }
     }
+    println!("Halt {}", camera.machine().get(0));
 
     let mut positions = vec![robot.unwrap()];
     let mut visited: BTreeSet<(i64, i64)> = BTreeSet::new();
     let mut intersections = vec![];
-    while positions.len() > 0 {
+    while !positions.is_empty() {
         let current_pos = positions.remove(0);
         if visited.contains(&current_pos) {
             continue;
@@ -301,12 +154,9 @@ fn main() {
         let mut d = (0, 1);
         loop {
             let next_pos = (current_pos.0 + d.0, current_pos.1 + d.1);
-            match map.get(&next_pos) {
-                Some('#') => {
-                    positions.push(next_pos);
-                    directions += 1;
-                }
-                _ => {}
+            if let Some('#') = map.get(&next_pos) {
+                positions.push(next_pos);
+                directions += 1;
             }
             d = right(d);
             if d == (0, 1) {
@@ -324,7 +174,7 @@ fn main() {
             .fold(0, |acc, x| acc + x.0.abs() * x.1.abs())
     );
 
-    pos = robot.unwrap();
+    let mut pos = robot.unwrap();
     let mut d = match map.get(&pos) {
         None => panic!(),
         Some('^') => (0, 1),
@@ -349,63 +199,27 @@ fn main() {
             },
         }
         let mut distance = 0;
-        loop {
-            match map.get(&(pos.0 + d.0, pos.1 + d.1)) {
-                Some('#') => {}
-                _ => break,
-            }
+        while let Some('#') = map.get(&(pos.0 + d.0, pos.1 + d.1)) {
             distance += 1;
             pos = (pos.0 + d.0, pos.1 + d.1);
         }
         commands += &format!("{},", distance);
     }
 
-    let letters = vec!["A".to_string(), "B".to_string(), "C".to_string()];
-    let (main_sequence, functions) = split_commands(&commands[..commands.len() - 1], vec![], letters.to_vec()).unwrap();
+    let letters = ["A".to_string(), "B".to_string(), "C".to_string()];
+    let (main_sequence, functions) =
+        split_commands(&commands[..commands.len() - 1], vec![], letters.to_vec()).unwrap();
 
-    let mut robot = State::new(&memory);
-    robot.memory.insert(0, 2);
-    let sequences = [
-        format!("{}\n", main_sequence),
-        format!("{}\n", functions[0]),
-        format!("{}\n", functions[1]),
-        format!("{}\n", functions[2]),
-        "n\n".to_string()
-    ];
-    let sequence = sequences.join("");
-    let mut it = sequence.as_bytes().iter();
-    let mut pos = (0, 0);
-    let mut map: BTreeMap<(i64, i64), char> = BTreeMap::new();
-    let mut lower_left = (0, 0);
-    let mut upper_right = (0, 0);
-
-    loop {
-        match robot.run() {
-            Output::Value(v) => {
-                if v >= 128 {
-                    println!("{}", v);
-                } else {
-                    pos = match v {
-                        10 => (0, pos.1 - 1),
-                        ch => {
-                            let ch = ch as u8 as char;
-                            lower_left.0 = std::cmp::min(lower_left.0, pos.0);
-                            lower_left.1 = std::cmp::min(lower_left.1, pos.1);
-                            upper_right.0 = std::cmp::max(upper_right.0, pos.0);
-                            upper_right.1 = std::cmp::max(upper_right.1, pos.1);
-                            map.insert(pos, ch);
-                            (pos.0 + 1, pos.1)
-                        }
-                    };
-                }
-            }
-            Output::Halt(_) => {
-                break;
-            }
-            Output::NeedsInput => match it.next() {
-                Some(ch) => robot.input = Some(*ch as i64),
-                None => panic!(),
-            },
-        }
+    let mut robot = Machine::new(&memory);
+    robot.set(0, 2);
+    let mut robot = AsciiMachine::from(robot);
+    robot.send_line(&main_sequence);
+    for function in &functions[..3] {
+        robot.send_line(function);
+    }
+    robot.send_line("n");
+    for v in robot.run()?.answers {
+        println!("{}", v);
     }
+    Ok(())
 }
@@ -0,0 +1,54 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use intcode::cached::CachedMachine;
+use intcode::{Machine, Output};
+
+const BOOST: &str = include_str!("../../09/input");
+const ARCADE: &str = include_str!("../../13/input");
+const ASCII: &str = include_str!("../../17/input");
+
+fn parse(program: &str) -> Vec<i64> {
+    intcode::read_program(program.as_bytes()).unwrap()
+}
+
+/// Runs a program to completion on the reference interpreter, answering
+/// every input request with `input`.
+fn reference(program: &[i64], input: i64) -> usize {
+    let mut machine = Machine::new(program);
+    let mut outputs = 0;
+    loop {
+        match machine.run().unwrap() {
+            Output::Value(_) => outputs += 1,
+            Output::NeedsInput => machine.input.push_back(input),
+            Output::Halt(_) => return outputs,
+        }
+    }
+}
+
+/// Like `reference`, on the cached engine.
+fn cached(program: &[i64], input: i64) -> usize {
+    let mut machine = CachedMachine::new(program);
+    let mut outputs = 0;
+    loop {
+        match machine.run().unwrap() {
+            Output::Value(_) => outputs += 1,
+            Output::NeedsInput => machine.input().push_back(input),
+            Output::Halt(_) => return outputs,
+        }
+    }
+}
+
+fn bench_program(c: &mut Criterion, name: &str, program: &[i64], input: i64) {
+    let mut group = c.benchmark_group(name);
+    group.bench_function("reference", |b| b.iter(|| reference(program, input)));
+    group.bench_function("cached", |b| b.iter(|| cached(program, input)));
+    group.finish();
+}
+
+fn engine(c: &mut Criterion) {
+    bench_program(c, "day09 boost", &parse(BOOST), 2);
+    bench_program(c, "day13 arcade", &parse(ARCADE), 0);
+    bench_program(c, "day17 camera", &parse(ASCII), 0);
+}
+
+criterion_group!(benches, engine);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use intcode::{Machine, Memory, Output, PagedMemory, VecMemory};
+use std::collections::BTreeMap;
+
+const BOOST: &str = include_str!("../../09/input");
+const ARCADE: &str = include_str!("../../13/input");
+const ASCII: &str = include_str!("../../17/input");
+
+fn parse(program: &str) -> Vec<i64> {
+    intcode::read_program(program.as_bytes()).unwrap()
+}
+
+/// Runs a program to completion, answering every input request with `input`.
+fn run<M: Memory<Word = i64>>(program: &[i64], input: i64) -> usize {
+    let mut machine = Machine::with_memory(M::load(program));
+    let mut outputs = 0;
+    loop {
+        match machine.run().unwrap() {
+            Output::Value(_) => outputs += 1,
+            Output::NeedsInput => machine.input.push_back(input),
+            Output::Halt(_) => return outputs,
+        }
+    }
+}
+
+fn bench_program(c: &mut Criterion, name: &str, program: &[i64], input: i64) {
+    let mut group = c.benchmark_group(name);
+    group.bench_function("vec", |b| b.iter(|| run::<VecMemory>(program, input)));
+    group.bench_function("paged", |b| b.iter(|| run::<PagedMemory>(program, input)));
+    group.bench_function("btree", |b| {
+        b.iter(|| run::<BTreeMap<usize, i64>>(program, input))
+    });
+    group.finish();
+}
+
+fn memory(c: &mut Criterion) {
+    bench_program(c, "day09 boost", &parse(BOOST), 2);
+    bench_program(c, "day13 arcade", &parse(ARCADE), 0);
+    bench_program(c, "day17 camera", &parse(ASCII), 0);
+}
+
+criterion_group!(benches, memory);
+criterion_main!(benches);
@@ -0,0 +1,258 @@
+//! How a program uses its memory: what it writes, whether it patches its own
+//! code and whether it reads memory nothing ever wrote.
+//!
+//! `Analysis::new` looks at the program statically, finding the reachable
+//! instructions and the fixed addresses they write to. Running the program
+//! through `Analysis::run` adds what actually happened, since relative-mode
+//! writes and computed jumps can only be seen at runtime.
+
+use crate::disasm;
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode};
+use crate::machine::{Machine, Output};
+use crate::memory::Memory;
+use crate::trace;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    image: Vec<i64>,
+    /// Instruction sizes by start address, for every instruction found
+    /// statically or executed.
+    code: BTreeMap<usize, usize>,
+    /// Instructions reachable from address 0.
+    pub reachable: BTreeSet<usize>,
+    /// Reachable instructions writing to a fixed address inside another
+    /// reachable instruction, as `(writer, address)`.
+    pub static_patches: Vec<(usize, usize)>,
+    /// Executions per instruction address.
+    pub executed: BTreeMap<usize, u64>,
+    /// Every address written at runtime, with the instructions writing it.
+    pub written: BTreeMap<usize, BTreeSet<usize>>,
+    /// Instructions executed while differing from the image, with how often.
+    pub patched: BTreeMap<usize, u64>,
+    /// Addresses beyond the image read before anything was written there,
+    /// with the instruction first reading each.
+    pub uninitialized: BTreeMap<usize, usize>,
+}
+
+impl Analysis {
+    /// Analyses `program` statically; the image is what patched code and
+    /// uninitialized reads are judged against.
+    pub fn new(program: &[i64]) -> Analysis {
+        let reachable = disasm::reachability(program).code;
+        let code = reachable
+            .iter()
+            .map(|&address| (address, size(program[address])))
+            .collect::<BTreeMap<_, _>>();
+        let mut analysis = Analysis {
+            image: program.to_vec(),
+            code,
+            reachable,
+            ..Analysis::default()
+        };
+        for &address in &analysis.reachable {
+            let instruction = Instruction::decode(program[address]).unwrap();
+            let target = instruction.opcode.write_param().and_then(|i| {
+                let param = program[address + 1 + i];
+                if instruction.modes[i] == Mode::Position && param >= 0 {
+                    Some(param as usize)
+                } else {
+                    None
+                }
+            });
+            if let Some(target) = target {
+                if analysis.owner(target).is_some() {
+                    analysis.static_patches.push((address, target));
+                }
+            }
+        }
+        analysis
+    }
+
+    /// The instruction covering `address`, if any is known.
+    pub fn owner(&self, address: usize) -> Option<usize> {
+        self.code
+            .range(..=address)
+            .next_back()
+            .filter(|&(&start, &size)| address < start + size)
+            .map(|(&start, _)| start)
+    }
+
+    /// Writes that landed inside known instructions, as
+    /// `(writer, address, instruction)`.
+    pub fn code_writes(&self) -> Vec<(usize, usize, usize)> {
+        self.written
+            .iter()
+            .filter_map(|(&address, writers)| Some((address, writers, self.owner(address)?)))
+            .flat_map(|(address, writers, owner)| {
+                writers.iter().map(move |&writer| (writer, address, owner))
+            })
+            .collect()
+    }
+
+    /// Executes one instruction with `trace::step`, recording its accesses.
+    pub fn step<M: Memory<Word = i64>>(
+        &mut self,
+        machine: &mut Machine<M>,
+    ) -> Result<Option<Output>, IntcodeError> {
+        let ip = machine.ip;
+        let words = size(machine.get(ip));
+        let patched = (ip..ip + words).any(|a| machine.get(a) != self.word(a));
+        let (output, event) = trace::step(machine)?;
+        let event = match event {
+            Some(event) => event,
+            None => return Ok(output),
+        };
+        *self.executed.entry(ip).or_insert(0) += 1;
+        self.code.entry(ip).or_insert(words);
+        if patched {
+            *self.patched.entry(ip).or_insert(0) += 1;
+        }
+        for operand in &event.operands {
+            if let (Some(address), Some(_)) = (operand.address, operand.value) {
+                if address >= self.image.len() && !self.written.contains_key(&address) {
+                    self.uninitialized.entry(address).or_insert(ip);
+                }
+            }
+        }
+        if let Some(write) = event.write {
+            self.written.entry(write.address).or_default().insert(ip);
+        }
+        Ok(output)
+    }
+
+    /// Like `Machine::run`, recording accesses along the way.
+    pub fn run<M: Memory<Word = i64>>(
+        &mut self,
+        machine: &mut Machine<M>,
+    ) -> Result<Output, IntcodeError> {
+        loop {
+            if let Some(output) = self.step(machine)? {
+                return Ok(output);
+            }
+        }
+    }
+
+    fn word(&self, address: usize) -> i64 {
+        self.image.get(address).copied().unwrap_or(0)
+    }
+
+    fn describe(&self, address: usize) -> String {
+        disasm::describe(|a| self.word(a), address).0
+    }
+
+    /// Summarises the findings, listing at most `limit` items per section.
+    pub fn report(&self, limit: usize) -> String {
+        let mut out = String::new();
+        writeln!(out, "image         {} words", self.image.len()).unwrap();
+        writeln!(out, "reachable     {} instructions", self.reachable.len()).unwrap();
+        writeln!(
+            out,
+            "executed      {} instructions, {} distinct, {} not found statically",
+            self.executed.values().sum::<u64>(),
+            self.executed.len(),
+            self.executed
+                .keys()
+                .filter(|a| !self.reachable.contains(a))
+                .count()
+        )
+        .unwrap();
+        let inside = self.written.range(..self.image.len()).count();
+        writeln!(
+            out,
+            "written       {} cells, {} inside the image, {} beyond",
+            self.written.len(),
+            inside,
+            self.written.len() - inside
+        )
+        .unwrap();
+
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "static patches: {} fixed writes into reachable code",
+            self.static_patches.len()
+        )
+        .unwrap();
+        for &(writer, address) in self.static_patches.iter().take(limit) {
+            let owner = self.owner(address).unwrap();
+            writeln!(
+                out,
+                "  {:>6}  {:<28} writes {:>6} in {:>6}  {}",
+                writer,
+                self.describe(writer),
+                address,
+                owner,
+                self.describe(owner)
+            )
+            .unwrap();
+        }
+
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "patched code: {} instructions executed after being modified",
+            self.patched.len()
+        )
+        .unwrap();
+        for (&address, &count) in self.patched.iter().take(limit) {
+            writeln!(
+                out,
+                "  {:>6}  {:<28} {} of {} executions patched",
+                address,
+                self.describe(address),
+                count,
+                self.executed[&address]
+            )
+            .unwrap();
+        }
+
+        let code_writes = self.code_writes();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "code writes: {} writes into instructions",
+            code_writes.len()
+        )
+        .unwrap();
+        for &(writer, address, owner) in code_writes.iter().take(limit) {
+            writeln!(
+                out,
+                "  {:>6}  {:<28} writes {:>6} in {:>6}  {}",
+                writer,
+                self.describe(writer),
+                address,
+                owner,
+                self.describe(owner)
+            )
+            .unwrap();
+        }
+
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "uninitialized reads: {} cells beyond the image read before being written",
+            self.uninitialized.len()
+        )
+        .unwrap();
+        for (&address, &reader) in self.uninitialized.iter().take(limit) {
+            writeln!(
+                out,
+                "  {:>6}  first read by {:>6}  {}",
+                address,
+                reader,
+                self.describe(reader)
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// Words taken by the instruction encoded as `raw`, counting words that do
+/// not decode as one.
+fn size(raw: i64) -> usize {
+    Instruction::decode(raw).map_or(1, |instruction| instruction.size())
+}
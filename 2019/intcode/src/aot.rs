@@ -0,0 +1,434 @@
+//! Ahead-of-time compilation of Intcode programs to Rust.
+//!
+//! `compile` turns a program into the source of a Rust module with one match
+//! arm per reachable instruction, its modes and parameters baked in. The
+//! module's `load` wraps a `Machine` in a `Compiled`, whose `run` behaves
+//! exactly like `Machine::run`.
+//!
+//! Compiled code is only right while the words it was compiled from are
+//! unchanged. Instructions the program patches through a fixed address are
+//! not compiled at all. Any other write landing on a compiled instruction,
+//! or a difference between the loaded memory and the compiled image, sends
+//! that instruction to the interpreter from then on. So does anything about
+//! to fail, leaving the interpreter to report the error, and every
+//! instruction of a machine in `checked` mode or with `limits` set. Only
+//! instructions run on the interpreter count towards `Machine::executed`.
+
+use crate::disasm;
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::machine::{Machine, Output};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+
+/// Words of the compiled image written per line.
+const IMAGE_PER_LINE: usize = 16;
+
+type Code = fn(&mut Compiled) -> Result<Output, IntcodeError>;
+
+/// A machine running on a compiled program.
+pub struct Compiled {
+    machine: Machine,
+    /// For each word of the image, one more than the address of the compiled
+    /// instruction it belongs to, or 0.
+    owner: &'static [u32],
+    /// Which compiled instructions still match memory, by address.
+    intact: Vec<bool>,
+    code: Code,
+}
+
+impl Compiled {
+    /// Used by the generated `load`.
+    pub fn new(machine: Machine, image: &[i64], owner: &'static [u32], code: Code) -> Compiled {
+        let mut compiled = Compiled {
+            machine,
+            owner,
+            intact: vec![false; owner.len()],
+            code,
+        };
+        for &owner in owner.iter().filter(|&&owner| owner > 0) {
+            compiled.intact[owner as usize - 1] = true;
+        }
+        for (address, &word) in image.iter().enumerate() {
+            if compiled.machine.get(address) != word {
+                compiled.touch(address);
+            }
+        }
+        compiled
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> Machine {
+        self.machine
+    }
+
+    /// Values waiting to be read by opcode 3, front first.
+    pub fn input(&mut self) -> &mut VecDeque<i64> {
+        &mut self.machine.input
+    }
+
+    pub fn get(&self, i: usize) -> i64 {
+        self.machine.get(i)
+    }
+
+    pub fn set(&mut self, i: usize, v: i64) {
+        self.machine.set(i, v);
+        self.touch(i);
+    }
+
+    fn touch(&mut self, i: usize) {
+        if let Some(&owner) = self.owner.get(i) {
+            if owner > 0 {
+                self.intact[owner as usize - 1] = false;
+            }
+        }
+    }
+
+    /// Runs until the program produces output, needs input or halts.
+    pub fn run(&mut self) -> Result<Output, IntcodeError> {
+        if self.machine.checked || !self.machine.limits.is_unlimited() {
+            loop {
+                if let Some(output) = self.step()? {
+                    return Ok(output);
+                }
+            }
+        }
+        (self.code)(self)
+    }
+
+    /// Executes the instruction at `ip` on the interpreter.
+    pub fn step(&mut self) -> Result<Option<Output>, IntcodeError> {
+        let instruction = self.machine.decode()?;
+        let (output, written) = self.machine.execute(instruction)?;
+        if let Some(i) = written {
+            self.touch(i);
+        }
+        Ok(output)
+    }
+
+    /// Whether the compiled instruction at `address` can still be used.
+    #[doc(hidden)]
+    pub fn intact(&self, address: usize) -> bool {
+        self.intact[address]
+    }
+
+    /// Hands the generated code's registers back to the machine.
+    #[doc(hidden)]
+    pub fn sync(&mut self, ip: usize, rb: i64) {
+        self.machine.ip = ip;
+        self.machine.rb = rb;
+    }
+
+    #[doc(hidden)]
+    pub fn pop_input(&mut self) -> Option<i64> {
+        self.machine.input.pop_front()
+    }
+}
+
+/// A relative-mode address, or `None` where the interpreter would fail.
+pub fn relative(rb: i64, offset: i64) -> Option<usize> {
+    rb.checked_add(offset)
+        .filter(|&address| address >= 0)
+        .map(|address| address as usize)
+}
+
+/// Generates a Rust module running `program`; `source` names it in the header.
+///
+/// The module needs the `intcode` crate and provides
+/// `pub fn load(machine: Machine) -> Compiled`.
+pub fn compile(program: &[i64], source: &str) -> String {
+    let reach = disasm::reachability(program);
+    let patched = reach
+        .code
+        .iter()
+        .filter_map(|&address| {
+            let instruction = Instruction::decode(program[address]).unwrap();
+            let i = instruction.opcode.write_param()?;
+            let param = program[address + 1 + i];
+            if instruction.modes[i] == Mode::Position && param >= 0 {
+                Some(param as usize)
+            } else {
+                None
+            }
+        })
+        .collect::<HashSet<_>>();
+
+    let mut owner = vec![0; program.len()];
+    let mut arms = String::new();
+    for &address in &reach.code {
+        let instruction = Instruction::decode(program[address]).unwrap();
+        let end = address + instruction.size();
+        if (address..end).any(|a| patched.contains(&a)) {
+            continue;
+        }
+        if let Some(arm) = arm(address, instruction, &program[address + 1..end]) {
+            for cell in &mut owner[address..end] {
+                *cell = address as u32 + 1;
+            }
+            arms.push_str(&arm);
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by intcode-aot from {}; do not edit.",
+        source
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "use intcode::aot::Compiled;").unwrap();
+    writeln!(out, "use intcode::{{IntcodeError, Machine, Output}};").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "static IMAGE: [i64; {}] = [", program.len()).unwrap();
+    write_table(&mut out, program);
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "static OWNER: [u32; {}] = [", owner.len()).unwrap();
+    write_table(&mut out, &owner);
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "pub fn load(machine: Machine) -> Compiled {{").unwrap();
+    writeln!(out, "    Compiled::new(machine, &IMAGE, &OWNER, run)").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "fn run(c: &mut Compiled) -> Result<Output, IntcodeError> {{"
+    )
+    .unwrap();
+    writeln!(out, "    let mut ip = c.machine().ip;").unwrap();
+    writeln!(out, "    let mut rb = c.machine().rb;").unwrap();
+    writeln!(out, "    loop {{").unwrap();
+    writeln!(out, "        match ip {{").unwrap();
+    out.push_str(&arms);
+    writeln!(out, "            _ => {{}}").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        c.sync(ip, rb);").unwrap();
+    writeln!(out, "        if let Some(output) = c.step()? {{").unwrap();
+    writeln!(out, "            return Ok(output);").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        ip = c.machine().ip;").unwrap();
+    writeln!(out, "        rb = c.machine().rb;").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn write_table<T: std::fmt::Display>(out: &mut String, words: &[T]) {
+    for line in words.chunks(IMAGE_PER_LINE) {
+        let words = line.iter().map(T::to_string).collect::<Vec<_>>();
+        writeln!(out, "    {},", words.join(", ")).unwrap();
+    }
+}
+
+/// The match arm for the instruction at `address`, or `None` if it can only
+/// fail and is best left to the interpreter.
+fn arm(address: usize, instruction: Instruction, params: &[i64]) -> Option<String> {
+    let Instruction { opcode, modes } = instruction;
+    if params
+        .iter()
+        .zip(&modes)
+        .any(|(&param, &mode)| mode == Mode::Position && param < 0)
+    {
+        return None;
+    }
+    // Relative addresses are worked out up front, so that an arm either
+    // completes or leaves the match without side effects.
+    let relative = params
+        .iter()
+        .zip(&modes)
+        .enumerate()
+        .filter(|(_, (_, &mode))| mode == Mode::Relative)
+        .map(|(i, (&param, _))| (format!("a{}", i), param))
+        .collect::<Vec<_>>();
+    let operand = |i: usize| -> Operand {
+        match modes[i] {
+            Mode::Immediate => Operand::Constant(params[i]),
+            Mode::Position => Operand::Expr(format!("c.get({})", params[i])),
+            Mode::Relative => Operand::Expr(format!("c.get(a{})", i)),
+        }
+    };
+    let target = |i: usize| -> Option<String> {
+        match modes[i] {
+            Mode::Immediate => None,
+            Mode::Position => Some(params[i].to_string()),
+            Mode::Relative => Some(format!("a{}", i)),
+        }
+    };
+
+    let mut body = vec![];
+    match opcode {
+        Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+            let (x, y) = (operand(0), operand(1));
+            let value = match (opcode, &x, &y) {
+                (Opcode::Add, Operand::Constant(x), Operand::Constant(y)) => {
+                    x.wrapping_add(*y).to_string()
+                }
+                (Opcode::Mul, Operand::Constant(x), Operand::Constant(y)) => {
+                    x.wrapping_mul(*y).to_string()
+                }
+                (Opcode::Lt, Operand::Constant(x), Operand::Constant(y)) => {
+                    i64::from(x < y).to_string()
+                }
+                (Opcode::Eq, Operand::Constant(x), Operand::Constant(y)) => {
+                    i64::from(x == y).to_string()
+                }
+                (Opcode::Lt, _, _) if x == y => "0".to_string(),
+                (Opcode::Eq, _, _) if x == y => "1".to_string(),
+                (Opcode::Add, _, _) => format!("{}.wrapping_add({})", x.receiver(), y),
+                (Opcode::Mul, _, _) => format!("{}.wrapping_mul({})", x.receiver(), y),
+                (Opcode::Lt, _, _) => format!("i64::from({} < {})", x, y),
+                _ => format!("i64::from({} == {})", x, y),
+            };
+            body.push(format!("c.set({}, {});", target(2)?, value));
+            body.push(format!("ip = {};", address + 4));
+            body.push("continue;".to_string());
+        }
+        Opcode::In => {
+            body.push("match c.pop_input() {".to_string());
+            body.push(format!("    Some(v) => c.set({}, v),", target(0)?));
+            body.push("    None => {".to_string());
+            body.push(format!("        c.sync({}, rb);", address));
+            body.push("        return Ok(Output::NeedsInput);".to_string());
+            body.push("    }".to_string());
+            body.push("}".to_string());
+            body.push(format!("ip = {};", address + 2));
+            body.push("continue;".to_string());
+        }
+        Opcode::Out => {
+            body.push(format!("let value = {};", operand(0)));
+            body.push(format!("c.sync({}, rb);", address + 2));
+            body.push("return Ok(Output::Value(value));".to_string());
+        }
+        Opcode::Jnz | Opcode::Jz => {
+            let mut taken = vec![];
+            match operand(1) {
+                Operand::Constant(d) if d < 0 => return None,
+                Operand::Constant(d) => {
+                    taken.push(format!("ip = {};", d));
+                    taken.push("continue;".to_string());
+                }
+                Operand::Expr(d) => {
+                    taken.push(format!("let d = {};", d));
+                    taken.push("if d >= 0 {".to_string());
+                    taken.push("    ip = d as usize;".to_string());
+                    taken.push("    continue;".to_string());
+                    taken.push("}".to_string());
+                }
+            }
+            let skip = vec![format!("ip = {};", address + 3), "continue;".to_string()];
+            let test = if opcode == Opcode::Jnz { "!=" } else { "==" };
+            match operand(0) {
+                Operand::Constant(cond) => {
+                    if (opcode == Opcode::Jnz) != (cond == 0) {
+                        body.extend(taken);
+                    } else {
+                        body.extend(skip);
+                    }
+                }
+                Operand::Expr(cond) => {
+                    body.push(format!("if {} {} 0 {{", cond, test));
+                    body.extend(taken.into_iter().map(|line| format!("    {}", line)));
+                    body.push("} else {".to_string());
+                    body.extend(skip.into_iter().map(|line| format!("    {}", line)));
+                    body.push("}".to_string());
+                }
+            }
+        }
+        Opcode::Arb => {
+            body.push(format!("rb = rb.wrapping_add({});", operand(0)));
+            body.push(format!("ip = {};", address + 2));
+            body.push("continue;".to_string());
+        }
+        Opcode::Hlt => {
+            body.push(format!("c.sync({}, rb);", address));
+            body.push("return Ok(Output::Halt(c.get(0)));".to_string());
+        }
+    }
+
+    let indent = if relative.is_empty() { 0 } else { 4 };
+    let mut arm = String::new();
+    writeln!(
+        arm,
+        "            // {}: {}",
+        address,
+        instruction.format(params)
+    )
+    .unwrap();
+    writeln!(
+        arm,
+        "            {} if c.intact({}) => {{",
+        address, address
+    )
+    .unwrap();
+    match relative.len() {
+        0 => {}
+        1 => writeln!(
+            arm,
+            "                if let Some({}) = intcode::aot::relative(rb, {}) {{",
+            relative[0].0, relative[0].1
+        )
+        .unwrap(),
+        _ => writeln!(
+            arm,
+            "                if let ({}) = ({}) {{",
+            relative
+                .iter()
+                .map(|(name, _)| format!("Some({})", name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            relative
+                .iter()
+                .map(|(_, offset)| format!("intcode::aot::relative(rb, {})", offset))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .unwrap(),
+    }
+    for line in body {
+        writeln!(
+            arm,
+            "                {:indent$}{}",
+            "",
+            line,
+            indent = indent
+        )
+        .unwrap();
+    }
+    if indent > 0 {
+        writeln!(arm, "                }}").unwrap();
+    }
+    writeln!(arm, "            }}").unwrap();
+    Some(arm)
+}
+
+/// A parameter as read by the generated code.
+#[derive(PartialEq)]
+enum Operand {
+    Constant(i64),
+    Expr(String),
+}
+
+impl Operand {
+    /// The operand written so a method can be called on it.
+    fn receiver(&self) -> String {
+        match self {
+            Operand::Constant(v) if *v < 0 => format!("({}i64)", v),
+            Operand::Constant(v) => format!("{}i64", v),
+            Operand::Expr(e) => e.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Operand::Constant(v) => write!(f, "{}", v),
+            Operand::Expr(e) => f.write_str(e),
+        }
+    }
+}
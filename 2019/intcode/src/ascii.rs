@@ -0,0 +1,157 @@
+//! Line-based text interaction with ASCII-capable programs.
+//!
+//! Day 17 talks to the outside world in text: the program prints lines of
+//! ASCII, reads commands as one character code per input and reports a
+//! final answer as a single value too large to be a character. `AsciiMachine` hides the character codes and hands out
+//! whole lines instead. `io::Ascii` does the same job as an `IoDevice`
+//! when a program only needs to be wired to a terminal.
+
+use crate::error::IntcodeError;
+use crate::machine::{Machine, Output};
+use crate::memory::{Memory, VecMemory};
+use std::io::{self, prelude::*};
+use std::mem;
+
+/// What an `AsciiMachine` stopped for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A line of output, without its newline.
+    Line(String),
+    /// A value outside the ASCII range, usually the puzzle answer.
+    Answer(i64),
+    /// The program wants a line of input.
+    NeedsInput,
+    Halt,
+}
+
+/// Everything a program printed until it halted or wanted input.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub lines: Vec<String>,
+    pub answers: Vec<i64>,
+    /// Whether the program halted rather than waiting for input.
+    pub halted: bool,
+}
+
+/// A machine whose output is read a line at a time.
+///
+/// Output that does not end in a newline, such as a prompt, is handed out
+/// as a line of its own before the program asks for input, prints an
+/// answer or halts.
+#[derive(Debug, Clone)]
+pub struct AsciiMachine<M: Memory<Word = i64> = VecMemory> {
+    machine: Machine<M>,
+    line: String,
+    pending: Option<Event>,
+}
+
+impl AsciiMachine {
+    pub fn new(memory: &[i64]) -> AsciiMachine {
+        AsciiMachine::from(Machine::new(memory))
+    }
+}
+
+impl<M: Memory<Word = i64>> From<Machine<M>> for AsciiMachine<M> {
+    fn from(machine: Machine<M>) -> AsciiMachine<M> {
+        AsciiMachine {
+            machine,
+            line: String::new(),
+            pending: None,
+        }
+    }
+}
+
+impl<M: Memory<Word = i64>> AsciiMachine<M> {
+    /// The machine state, for inspecting ip, rb or memory.
+    pub fn machine(&self) -> &Machine<M> {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> Machine<M> {
+        self.machine
+    }
+
+    /// Queues `line` as input, followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        let input = &mut self.machine.input;
+        input.extend(line.bytes().map(i64::from));
+        input.push_back(10);
+        if self.pending == Some(Event::NeedsInput) {
+            self.pending = None;
+        }
+    }
+
+    /// Runs until the program completes a line, prints an answer, needs
+    /// input or halts.
+    pub fn next_event(&mut self) -> Result<Event, IntcodeError> {
+        if let Some(event) = self.pending.take() {
+            return Ok(event);
+        }
+        let event = loop {
+            match self.machine.run()? {
+                Output::Value(10) => return Ok(Event::Line(mem::take(&mut self.line))),
+                Output::Value(v @ 0..=127) => self.line.push(v as u8 as char),
+                Output::Value(v) => break Event::Answer(v),
+                Output::NeedsInput => break Event::NeedsInput,
+                Output::Halt(_) => break Event::Halt,
+            }
+        };
+        if self.line.is_empty() {
+            return Ok(event);
+        }
+        self.pending = Some(event);
+        Ok(Event::Line(mem::take(&mut self.line)))
+    }
+
+    /// Runs until the program halts or needs input, collecting its output.
+    pub fn run(&mut self) -> Result<Transcript, IntcodeError> {
+        let mut transcript = Transcript::default();
+        loop {
+            match self.next_event()? {
+                Event::Line(line) => transcript.lines.push(line),
+                Event::Answer(v) => transcript.answers.push(v),
+                Event::NeedsInput => return Ok(transcript),
+                Event::Halt => {
+                    transcript.halted = true;
+                    return Ok(transcript);
+                }
+            }
+        }
+    }
+
+    /// Plays the program from `reader`, echoing its output to `writer`.
+    ///
+    /// Answers are written as numbers on lines of their own. Returns
+    /// `Event::Halt`, or `Event::NeedsInput` once `reader` runs out. Failing
+    /// to read or write is an `IntcodeError::Device` at the current `ip`.
+    pub fn interact<R: BufRead, W: Write>(
+        &mut self,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<Event, IntcodeError> {
+        loop {
+            let event = self.next_event()?;
+            let result = match &event {
+                Event::Line(line) => writeln!(writer, "{}", line),
+                Event::Answer(v) => writeln!(writer, "{}", v),
+                Event::NeedsInput | Event::Halt => writer.flush(),
+            };
+            result.map_err(|e| self.device_error(e))?;
+            if event == Event::Halt {
+                return Ok(Event::Halt);
+            }
+            if event == Event::NeedsInput {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) => return Ok(Event::NeedsInput),
+                    Ok(_) => self.send_line(line.trim_end_matches(&['\r', '\n'][..])),
+                    Err(e) => return Err(self.device_error(e)),
+                }
+            }
+        }
+    }
+
+    fn device_error(&self, error: io::Error) -> IntcodeError {
+        self.machine.device_error(self.machine.ip, error)
+    }
+}
@@ -0,0 +1,366 @@
+//! A small assembler for Intcode.
+//!
+//! The syntax mirrors the disassembler output: one instruction per line,
+//! operands written as `#imm`, `[addr]` or `[rb+off]`, `;` starting a comment
+//! and `name:` defining a label. Immediates and addresses may be numbers,
+//! labels or `label+offset`. `db` emits data words and string literals.
+//!
+//! The stack macros treat `rb` as a stack pointer to the next free cell, so a
+//! program using them should start with `arb #stack` pointing past its image:
+//!
+//! * `push x` stores `x` at `[rb+0]` and bumps `rb`,
+//! * `pop [dst]` drops `rb` and moves `[rb+0]` to `dst` (a relative `dst` is
+//!   taken from the lowered `rb`),
+//! * `call f` pushes the return address and jumps to `f`,
+//! * `ret` pops the return address and jumps to it,
+//! * `jmp f` jumps unconditionally.
+
+use crate::instruction::{Instruction, Mode, Opcode};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    /// One-based source line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Instruction(Opcode, Vec<(Mode, Expr)>),
+    Data(Vec<Expr>),
+}
+
+impl Item {
+    fn size(&self) -> usize {
+        match self {
+            Item::Instruction(opcode, _) => opcode.params() + 1,
+            Item::Data(words) => words.len(),
+        }
+    }
+}
+
+/// Assembles source text into an Intcode program.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels: HashMap<String, i64> = HashMap::new();
+    let mut items: Vec<(usize, Item)> = vec![];
+    let mut address = 0;
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| AsmError { line, message };
+        let mut text = strip_comment(text).trim();
+        while let Some(colon) = label_end(text) {
+            let name = text[..colon].trim();
+            if labels.insert(name.to_string(), address as i64).is_some() {
+                return Err(error(format!("duplicate label `{}`", name)));
+            }
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let (mnemonic, rest) = match text.find(char::is_whitespace) {
+            Some(i) => (&text[..i], text[i..].trim()),
+            None => (text, ""),
+        };
+        for item in expand(mnemonic, rest, address).map_err(error)? {
+            address += item.size();
+            items.push((line, item));
+        }
+    }
+
+    let mut program = vec![];
+    for (line, item) in items {
+        let resolve = |expr: &Expr| match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Label(name, offset) => match labels.get(name) {
+                Some(address) => address.checked_add(*offset).ok_or_else(|| AsmError {
+                    line,
+                    message: format!("`{}{:+}` is out of range", name, offset),
+                }),
+                None => Err(AsmError {
+                    line,
+                    message: format!("undefined label `{}`", name),
+                }),
+            },
+        };
+        match item {
+            Item::Instruction(opcode, operands) => {
+                let mut modes = [Mode::Position; 3];
+                for (m, (mode, _)) in modes.iter_mut().zip(operands.iter()) {
+                    *m = *mode;
+                }
+                program.push(Instruction { opcode, modes }.encode());
+                for (_, expr) in operands.iter() {
+                    program.push(resolve(expr)?);
+                }
+            }
+            Item::Data(words) => {
+                for expr in words.iter() {
+                    program.push(resolve(expr)?);
+                }
+            }
+        }
+    }
+    Ok(program)
+}
+
+/// Turns one source statement into the items it assembles to.
+fn expand(mnemonic: &str, rest: &str, address: usize) -> Result<Vec<Item>, String> {
+    let operands = split_operands(rest);
+    let arity = |n: usize| {
+        if operands.len() == n {
+            Ok(())
+        } else {
+            Err(format!(
+                "`{}` takes {} operand(s), found {}",
+                mnemonic,
+                n,
+                operands.len()
+            ))
+        }
+    };
+    let top = (Mode::Relative, Expr::Number(0));
+    let zero = (Mode::Immediate, Expr::Number(0));
+    let one = (Mode::Immediate, Expr::Number(1));
+    let arb = |n: i64| Item::Instruction(Opcode::Arb, vec![(Mode::Immediate, Expr::Number(n))]);
+    match mnemonic {
+        "db" => Ok(vec![Item::Data(
+            operands
+                .iter()
+                .map(|o| parse_data(o))
+                .collect::<Result<Vec<Vec<Expr>>, String>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+        )]),
+        "push" => {
+            arity(1)?;
+            let value = parse_operand(&operands[0])?;
+            Ok(vec![
+                Item::Instruction(Opcode::Add, vec![value, zero, top]),
+                arb(1),
+            ])
+        }
+        "pop" => {
+            arity(1)?;
+            let to = parse_operand(&operands[0])?;
+            check_writable(mnemonic, &to)?;
+            Ok(vec![
+                arb(-1),
+                Item::Instruction(Opcode::Add, vec![top, zero, to]),
+            ])
+        }
+        "call" => {
+            arity(1)?;
+            let target = parse_target(&operands[0])?;
+            // add (4 words) + arb (2 words) + jnz (3 words)
+            let back = (Mode::Immediate, Expr::Number(address as i64 + 9));
+            Ok(vec![
+                Item::Instruction(Opcode::Add, vec![back, zero, top]),
+                arb(1),
+                Item::Instruction(Opcode::Jnz, vec![one, target]),
+            ])
+        }
+        "ret" => {
+            arity(0)?;
+            Ok(vec![
+                arb(-1),
+                Item::Instruction(Opcode::Jz, vec![zero, top]),
+            ])
+        }
+        "jmp" => {
+            arity(1)?;
+            let target = parse_target(&operands[0])?;
+            Ok(vec![Item::Instruction(Opcode::Jnz, vec![one, target])])
+        }
+        _ => {
+            let opcode = Opcode::from_mnemonic(mnemonic)
+                .ok_or_else(|| format!("unknown mnemonic `{}`", mnemonic))?;
+            arity(opcode.params())?;
+            let operands = operands
+                .iter()
+                .map(|o| parse_operand(o))
+                .collect::<Result<Vec<(Mode, Expr)>, String>>()?;
+            if let Some(i) = opcode.write_param() {
+                check_writable(mnemonic, &operands[i])?;
+            }
+            Ok(vec![Item::Instruction(opcode, operands)])
+        }
+    }
+}
+
+fn check_writable(mnemonic: &str, operand: &(Mode, Expr)) -> Result<(), String> {
+    if operand.0 == Mode::Immediate {
+        Err(format!("`{}` cannot write to an immediate", mnemonic))
+    } else {
+        Ok(())
+    }
+}
+
+fn parse_operand(text: &str) -> Result<(Mode, Expr), String> {
+    if let Some(imm) = text.strip_prefix('#') {
+        return Ok((Mode::Immediate, parse_expr(imm)?));
+    }
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        let inner = inner.trim();
+        if let Some(offset) = inner.strip_prefix("rb").map(str::trim) {
+            if offset.is_empty() {
+                return Ok((Mode::Relative, Expr::Number(0)));
+            } else if let Some(offset) = offset.strip_prefix('+') {
+                return Ok((Mode::Relative, parse_expr(offset)?));
+            } else if offset.starts_with('-') {
+                return Ok((Mode::Relative, parse_expr(offset)?));
+            }
+        }
+        return Ok((Mode::Position, parse_expr(inner)?));
+    }
+    Err(format!(
+        "expected `#imm`, `[addr]` or `[rb+off]`, found `{}`",
+        text
+    ))
+}
+
+/// Jump targets may omit the `#` since they are nearly always immediate.
+fn parse_target(text: &str) -> Result<(Mode, Expr), String> {
+    if text.starts_with('#') || text.starts_with('[') {
+        parse_operand(text)
+    } else {
+        Ok((Mode::Immediate, parse_expr(text)?))
+    }
+}
+
+fn parse_data(text: &str) -> Result<Vec<Expr>, String> {
+    match text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        Some(string) => Ok(unescape(string)?
+            .bytes()
+            .map(|b| Expr::Number(b as i64))
+            .collect()),
+        None => Ok(vec![parse_expr(text)?]),
+    }
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('\\') => out.push('\\'),
+            Some('"') => out.push('"'),
+            c => return Err(format!("invalid escape `\\{}`", c.unwrap_or(' '))),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_expr(text: &str) -> Result<Expr, String> {
+    let text = text.trim();
+    if let Ok(n) = text.parse::<i64>() {
+        return Ok(Expr::Number(n));
+    }
+    if text.is_empty() {
+        return Err("missing operand".to_string());
+    }
+    let sign = text
+        .char_indices()
+        .skip(1)
+        .filter(|&(_, c)| c == '+' || c == '-')
+        .last();
+    let (name, offset) = match sign {
+        Some((i, _)) => {
+            let (name, offset) = text.split_at(i);
+            let offset = offset.trim_start_matches('+').trim();
+            let offset = offset
+                .parse::<i64>()
+                .map_err(|_| format!("invalid offset in `{}`", text))?;
+            (name.trim(), offset)
+        }
+        None => (text, 0),
+    };
+    if !is_identifier(name) {
+        return Err(format!("expected a number or label, found `{}`", text));
+    }
+    Ok(Expr::Label(name.to_string(), offset))
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Position of the colon ending a leading label, if the line starts with one.
+fn label_end(text: &str) -> Option<usize> {
+    let colon = text.find(':')?;
+    if is_identifier(text[..colon].trim()) {
+        Some(colon)
+    } else {
+        None
+    }
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &text[..i],
+            _ => {}
+        }
+    }
+    text
+}
+
+fn split_operands(text: &str) -> Vec<String> {
+    if text.trim().is_empty() {
+        return vec![];
+    }
+    let mut operands = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                operands.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    operands.push(current.trim().to_string());
+    operands
+}
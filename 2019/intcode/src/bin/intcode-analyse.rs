@@ -0,0 +1,53 @@
+extern crate clap;
+
+use clap::{App, Arg};
+use intcode::analysis::Analysis;
+use intcode::{cli, Machine, Output};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = App::new("INTCODE memory analyser")
+        .arg_from_usage("--input [LIST] 'Comma-separated input values'")
+        .arg_from_usage("--ascii [TEXT] 'Input given as text, \\n standing for a newline'")
+        .arg(
+            Arg::from_usage("--poke [ADDR=VAL]... 'Patch memory before running'")
+                .number_of_values(1),
+        )
+        .arg_from_usage("--limit [N] 'Number of findings to list per section (default 20)'")
+        .arg_from_usage("--static 'Only analyse the program, without running it'")
+        .arg_from_usage("<FILE> 'Program to analyse'")
+        .get_matches();
+    let path = matches.value_of("FILE").unwrap();
+    let memory = intcode::read_program(BufReader::new(File::open(path)?))?;
+    let mut machine = Machine::new(&memory);
+    let poked = cli::poke(&mut machine, &matches)?;
+    machine.input.extend(cli::input(&matches)?);
+    let limit = matches.value_of("limit").map_or(Ok(20), str::parse)?;
+
+    // Pokes are part of the program as run, so they are not reported as
+    // patches, nor are cells they write beyond the program uninitialized.
+    let end = poked.iter().map(|a| a + 1).fold(memory.len(), usize::max);
+    let image = (0..end).map(|a| machine.get(a)).collect::<Vec<_>>();
+    let mut analysis = Analysis::new(&image);
+    while !matches.is_present("static") {
+        match analysis.run(&mut machine) {
+            Ok(Output::Value(_)) => {}
+            Ok(Output::NeedsInput) => {
+                eprintln!("stopped: program needs more input");
+                break;
+            }
+            Ok(Output::Halt(v)) => {
+                eprintln!("halt {}", v);
+                break;
+            }
+            Err(e) => {
+                eprintln!("stopped: {}", e);
+                break;
+            }
+        }
+    }
+    print!("{}", analysis.report(limit));
+    Ok(())
+}
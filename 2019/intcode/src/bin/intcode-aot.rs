@@ -0,0 +1,24 @@
+extern crate clap;
+
+use clap::App;
+use intcode::aot;
+use std::fs::{self, File};
+use std::io;
+use std::io::BufReader;
+
+fn main() -> io::Result<()> {
+    let matches = App::new("INTCODE ahead-of-time compiler")
+        .arg_from_usage("-o, --output [OUT] 'Rust file to write, stdout if omitted'")
+        .arg_from_usage("<FILE> 'Program to compile'")
+        .get_matches();
+    let path = matches.value_of("FILE").unwrap();
+    let memory = intcode::read_program(BufReader::new(File::open(path)?))?;
+    let source = aot::compile(&memory, path);
+    match matches.value_of("output") {
+        Some(out) => fs::write(out, source),
+        None => {
+            print!("{}", source);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,32 @@
+extern crate clap;
+
+use clap::App;
+use intcode::asm;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = App::new("INTCODE assembler")
+        .arg_from_usage("[FILE] 'Assembly source, read from stdin if omitted'")
+        .get_matches();
+    let source = match matches.value_of("FILE") {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            source
+        }
+    };
+    let program = asm::assemble(&source)?;
+    println!(
+        "{}",
+        program
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    );
+    Ok(())
+}
@@ -0,0 +1,25 @@
+extern crate clap;
+
+use clap::App;
+use intcode::cfg;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+
+fn main() -> io::Result<()> {
+    let matches = App::new("INTCODE control-flow graph")
+        .arg_from_usage("--json 'Print the graph as JSON rather than Graphviz DOT'")
+        .arg_from_usage("[FILE] 'Program to analyse, read from stdin if omitted'")
+        .get_matches();
+    let memory = match matches.value_of("FILE") {
+        Some(path) => intcode::read_program(BufReader::new(File::open(path)?))?,
+        None => intcode::read_program(io::stdin().lock())?,
+    };
+    let graph = cfg::build(&memory);
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string_pretty(&graph)?);
+    } else {
+        print!("{}", graph.to_dot());
+    }
+    Ok(())
+}
@@ -0,0 +1,329 @@
+extern crate clap;
+
+use clap::{App, Arg};
+use intcode::{disasm, Instruction, Machine, Opcode, Output};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+const HELP: &str = "\
+commands:
+  s, step [N]            execute N instructions (default 1)
+  c, continue            run until a breakpoint, watchpoint, halt or missing input
+  b, break ADDR          stop before executing the instruction at ADDR
+  d, delete ADDR         remove the breakpoint at ADDR
+  w, watch ADDR          stop after mem[ADDR] changes
+  unwatch ADDR           remove the watchpoint on mem[ADDR]
+  i, info                show ip, rb, queued input, breakpoints and watchpoints
+  l, list [ADDR] [N]     disassemble N instructions from ADDR (default ip)
+  h, history [N]         show the last N executed instructions
+  p, print mem[A..B]     print memory cells A to B (exclusive), or mem[A]
+  p, print ip|rb         print a register
+  set mem[A] = V         write V to memory
+  set ip|rb = V          write V to a register
+  input V...             queue input values
+  help                   show this text
+  q, quit                exit
+an empty line repeats the previous command";
+
+struct Executed {
+    ip: usize,
+    rb: i64,
+    text: String,
+}
+
+struct Debugger {
+    machine: Machine,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, i64>,
+    history: VecDeque<Executed>,
+    history_size: usize,
+    halted: bool,
+}
+
+impl Debugger {
+    fn describe(&self, address: usize) -> (String, usize) {
+        disasm::describe(|a| self.machine.get(a), address)
+    }
+
+    /// Executes one instruction, returning why execution should stop, if it should.
+    fn step(&mut self) -> Option<String> {
+        if self.halted {
+            return Some("program has halted".to_string());
+        }
+        let ip = self.machine.ip;
+        let next = Instruction::decode(self.machine.get(ip)).map(|i| i.opcode);
+        if next == Ok(Opcode::In) && self.machine.input.is_empty() {
+            return Some("waiting for input, queue some with `input`".to_string());
+        }
+        let executed = Executed {
+            ip,
+            rb: self.machine.rb,
+            text: self.describe(ip).0,
+        };
+        let result = self.machine.step();
+        if result.is_ok() && self.history_size > 0 {
+            if self.history.len() == self.history_size {
+                self.history.pop_front();
+            }
+            self.history.push_back(executed);
+        }
+        match result {
+            Err(e) => return Some(format!("error: {}", e)),
+            Ok(Some(Output::Halt(v))) => {
+                self.halted = true;
+                return Some(format!("halted, mem[0] = {}", v));
+            }
+            Ok(Some(Output::Value(v))) => println!("output: {}", v),
+            Ok(Some(Output::NeedsInput)) | Ok(None) => {}
+        }
+        let mut stop = None;
+        for (&address, old) in self.watchpoints.iter_mut() {
+            let new = self.machine.get(address);
+            if new != *old {
+                stop = Some(format!(
+                    "watchpoint: mem[{}] {} -> {} (by ip {})",
+                    address, old, new, ip
+                ));
+                *old = new;
+            }
+        }
+        if stop.is_none() && self.breakpoints.contains(&self.machine.ip) {
+            stop = Some(format!("breakpoint at {}", self.machine.ip));
+        }
+        stop
+    }
+
+    fn show_next(&self) {
+        let ip = self.machine.ip;
+        println!("{:6}  {}", ip, self.describe(ip).0);
+    }
+
+    fn execute(&mut self, command: &str, args: &[&str]) -> Result<bool, String> {
+        match command {
+            "s" | "step" => {
+                let count = parse_or(args.first(), 1)?;
+                for _ in 0..count {
+                    if let Some(reason) = self.step() {
+                        println!("{}", reason);
+                        break;
+                    }
+                }
+                self.show_next();
+            }
+            "c" | "continue" => {
+                let reason = loop {
+                    if let Some(reason) = self.step() {
+                        break reason;
+                    }
+                };
+                println!("{}", reason);
+                self.show_next();
+            }
+            "b" | "break" => {
+                let address = parse_arg(args.first())?;
+                self.breakpoints.insert(address);
+            }
+            "d" | "delete" => {
+                let address = parse_arg(args.first())?;
+                if !self.breakpoints.remove(&address) {
+                    return Err(format!("no breakpoint at {}", address));
+                }
+            }
+            "w" | "watch" => {
+                let address = parse_arg(args.first())?;
+                self.watchpoints.insert(address, self.machine.get(address));
+            }
+            "unwatch" => {
+                let address = parse_arg(args.first())?;
+                if self.watchpoints.remove(&address).is_none() {
+                    return Err(format!("no watchpoint on mem[{}]", address));
+                }
+            }
+            "i" | "info" => {
+                println!("ip {}  rb {}", self.machine.ip, self.machine.rb);
+                println!("input {:?}", self.machine.input);
+                println!("breakpoints {:?}", self.breakpoints);
+                println!("watchpoints {:?}", self.watchpoints.keys());
+                self.show_next();
+            }
+            "l" | "list" => {
+                let mut address = parse_or(args.first(), self.machine.ip)?;
+                for _ in 0..parse_or(args.get(1), 10)? {
+                    let (text, size) = self.describe(address);
+                    let marker = if address == self.machine.ip {
+                        "=>"
+                    } else {
+                        "  "
+                    };
+                    println!("{} {:6}  {}", marker, address, text);
+                    address += size;
+                }
+            }
+            "h" | "history" if self.history_size == 0 => {
+                return Err("history is disabled, restart with --history N".to_string());
+            }
+            "h" | "history" => {
+                let count = parse_or(args.first(), 10)?;
+                let skip = self.history.len().saturating_sub(count);
+                for executed in self.history.iter().skip(skip) {
+                    println!(
+                        "{:6}  rb {:<6}  {}",
+                        executed.ip, executed.rb, executed.text
+                    );
+                }
+            }
+            "p" | "print" => match args.first() {
+                Some(&"ip") => println!("ip = {}", self.machine.ip),
+                Some(&"rb") => println!("rb = {}", self.machine.rb),
+                Some(cells) => {
+                    let (from, to) = parse_cells(cells)?;
+                    for address in from..to {
+                        println!("mem[{}] = {}", address, self.machine.get(address));
+                    }
+                }
+                None => return Err("print what?".to_string()),
+            },
+            "set" => {
+                if args.len() != 3 || args[1] != "=" {
+                    return Err("usage: set mem[A] = V | set ip = V | set rb = V".to_string());
+                }
+                let value = parse_value(args[2])?;
+                match args[0] {
+                    "ip" if value >= 0 => self.machine.ip = value as usize,
+                    "ip" => return Err("ip cannot be negative".to_string()),
+                    "rb" => self.machine.rb = value,
+                    cells => {
+                        let (from, to) = parse_cells(cells)?;
+                        if to != from + 1 {
+                            return Err("set writes a single cell".to_string());
+                        }
+                        self.machine.set(from, value);
+                        if let Some(old) = self.watchpoints.get_mut(&from) {
+                            *old = value;
+                        }
+                    }
+                }
+                self.halted = false;
+            }
+            "input" => {
+                for arg in args {
+                    self.machine.input.push_back(parse_value(arg)?);
+                }
+            }
+            "help" => println!("{}", HELP),
+            "q" | "quit" => return Ok(false),
+            _ => return Err(format!("unknown command `{}`, try `help`", command)),
+        }
+        Ok(true)
+    }
+}
+
+fn parse_value(text: &str) -> Result<i64, String> {
+    text.parse()
+        .map_err(|_| format!("expected a number, found `{}`", text))
+}
+
+fn parse_list(list: &str) -> Result<Vec<i64>, String> {
+    list.split(',').map(|s| parse_value(s.trim())).collect()
+}
+
+fn parse_arg(arg: Option<&&str>) -> Result<usize, String> {
+    match arg {
+        Some(text) => text
+            .parse()
+            .map_err(|_| format!("expected an address, found `{}`", text)),
+        None => Err("missing argument".to_string()),
+    }
+}
+
+fn parse_or(arg: Option<&&str>, default: usize) -> Result<usize, String> {
+    match arg {
+        Some(_) => parse_arg(arg),
+        None => Ok(default),
+    }
+}
+
+/// Parses `mem[A]` or `mem[A..B]` into a half-open range of addresses.
+fn parse_cells(text: &str) -> Result<(usize, usize), String> {
+    let inner = text
+        .strip_prefix("mem[")
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(|| format!("expected mem[A] or mem[A..B], found `{}`", text))?;
+    match inner.find("..") {
+        Some(i) => {
+            let from = parse_arg(Some(&&inner[..i]))?;
+            let to = parse_arg(Some(&&inner[i + 2..]))?;
+            if to < from {
+                return Err(format!("empty range {}..{}", from, to));
+            }
+            Ok((from, to))
+        }
+        None => {
+            let address = parse_arg(Some(&inner))?;
+            Ok((address, address + 1))
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let matches = App::new("INTCODE debugger")
+        .arg(
+            Arg::from_usage("--input [LIST] 'Comma-separated values to queue as input'")
+                .validator(|list| parse_list(&list).map(|_| ())),
+        )
+        .arg(
+            Arg::from_usage(
+                "--history [N] 'Number of executed instructions to remember, 0 for none'",
+            )
+            .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())),
+        )
+        .arg_from_usage("<FILE> 'Program to debug'")
+        .get_matches();
+    let memory = intcode::read_program(BufReader::new(File::open(
+        matches.value_of("FILE").unwrap(),
+    )?))?;
+    let mut machine = Machine::new(&memory);
+    if let Some(list) = matches.value_of("input") {
+        machine.input.extend(parse_list(list).unwrap());
+    }
+    let mut debugger = Debugger {
+        machine,
+        breakpoints: BTreeSet::new(),
+        watchpoints: BTreeMap::new(),
+        history: VecDeque::new(),
+        history_size: matches
+            .value_of("history")
+            .map_or(1000, |n| n.parse().unwrap()),
+        halted: false,
+    };
+    debugger.show_next();
+    let stdin = io::stdin();
+    let mut last = String::new();
+    loop {
+        print!("(idbg) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            line = last.clone();
+        }
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let args = words.collect::<Vec<&str>>();
+        match debugger.execute(command, &args) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(message) => println!("{}", message),
+        }
+        last = line;
+    }
+    Ok(())
+}
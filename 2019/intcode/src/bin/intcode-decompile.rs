@@ -0,0 +1,32 @@
+extern crate clap;
+
+use clap::App;
+use intcode::decompile;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::process;
+
+fn main() -> io::Result<()> {
+    let matches = App::new("INTCODE decompiler")
+        .arg_from_usage("--check 'Recompile the result and compare it with the program'")
+        .arg_from_usage("[FILE] 'Program to decompile, read from stdin if omitted'")
+        .get_matches();
+    let memory = match matches.value_of("FILE") {
+        Some(path) => intcode::read_program(BufReader::new(File::open(path)?))?,
+        None => intcode::read_program(io::stdin().lock())?,
+    };
+    let decompiled = decompile::decompile(&memory);
+    print!("{}", decompiled.render());
+    if matches.is_present("check") {
+        let recompiled = decompiled.recompile();
+        match (0..memory.len()).find(|&i| recompiled.get(i) != memory.get(i)) {
+            Some(address) => {
+                eprintln!("recompiled program differs at address {}", address);
+                process::exit(1);
+            }
+            None => eprintln!("recompiled program matches"),
+        }
+    }
+    Ok(())
+}
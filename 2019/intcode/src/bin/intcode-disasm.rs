@@ -0,0 +1,25 @@
+extern crate clap;
+
+use clap::App;
+use intcode::disasm;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+
+fn main() -> io::Result<()> {
+    let matches = App::new("INTCODE disassembler")
+        .arg_from_usage("--json 'Print the listing as JSON'")
+        .arg_from_usage("[FILE] 'Program to disassemble, read from stdin if omitted'")
+        .get_matches();
+    let memory = match matches.value_of("FILE") {
+        Some(path) => intcode::read_program(BufReader::new(File::open(path)?))?,
+        None => intcode::read_program(io::stdin().lock())?,
+    };
+    let lines = disasm::disassemble(&memory);
+    if matches.is_present("json") {
+        println!("{}", serde_json::to_string_pretty(&lines)?);
+    } else {
+        print!("{}", disasm::render(&lines));
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+extern crate clap;
+
+use clap::{App, Arg};
+use intcode::profile::Profile;
+use intcode::{cli, Machine, Output};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = App::new("INTCODE profiler")
+        .arg_from_usage("--input [LIST] 'Comma-separated input values'")
+        .arg_from_usage("--ascii [TEXT] 'Input given as text, \\n standing for a newline'")
+        .arg(
+            Arg::from_usage("--poke [ADDR=VAL]... 'Patch memory before running'")
+                .number_of_values(1),
+        )
+        .arg_from_usage("--top [N] 'Number of hot spots to list (default 20)'")
+        .arg_from_usage("<FILE> 'Program to profile'")
+        .get_matches();
+    let path = matches.value_of("FILE").unwrap();
+    let memory = intcode::read_program(BufReader::new(File::open(path)?))?;
+    let mut machine = Machine::new(&memory);
+    cli::poke(&mut machine, &matches)?;
+    machine.input.extend(cli::input(&matches)?);
+    let top = matches.value_of("top").map_or(Ok(20), str::parse)?;
+
+    let mut profile = Profile::new();
+    loop {
+        match profile.run(&mut machine)? {
+            Output::Value(_) => {}
+            Output::NeedsInput => {
+                eprintln!("stopped: program needs more input");
+                break;
+            }
+            Output::Halt(v) => {
+                eprintln!("halt {}", v);
+                break;
+            }
+        }
+    }
+    print!("{}", profile.report(|a| machine.get(a), top));
+    Ok(())
+}
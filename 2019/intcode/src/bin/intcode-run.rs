@@ -0,0 +1,153 @@
+extern crate clap;
+
+use clap::{App, Arg};
+use intcode::ascii::{AsciiMachine, Event};
+use intcode::{cli, IntcodeError, Machine, Output};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+const HALTED: i32 = 0;
+const STARVED: i32 = 2;
+const FAULT: i32 = 3;
+const EXHAUSTED: i32 = 4;
+
+const EXIT_CODES: &str = "EXIT CODES:
+    0    the program halted
+    1    bad arguments or unreadable files
+    2    the program needs more input than was given
+    3    the program failed
+    4    a limit was reached";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Numeric,
+    Ascii,
+    Mixed,
+}
+
+/// Whether `value` can be written in `format`.
+fn printable(format: Format, value: i64) -> bool {
+    format != Format::Ascii || (0..=255).contains(&value)
+}
+
+fn write_value(out: &mut impl Write, format: Format, value: i64) -> io::Result<()> {
+    match format {
+        Format::Numeric => writeln!(out, "{}", value),
+        Format::Mixed if (0..=127).contains(&value) => out.write_all(&[value as u8]),
+        Format::Mixed => writeln!(out, "{}", value),
+        Format::Ascii => out.write_all(&[value as u8]),
+    }
+}
+
+/// The instant `seconds` from now.
+fn deadline(seconds: &str) -> Result<Instant, Box<dyn Error>> {
+    let timeout = Duration::try_from_secs_f64(seconds.parse()?)
+        .map_err(|e| format!("invalid timeout `{}`: {}", seconds, e))?;
+    let deadline = Instant::now()
+        .checked_add(timeout)
+        .ok_or_else(|| format!("timeout `{}` is too long", seconds))?;
+    Ok(deadline)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = App::new("INTCODE runner")
+        .arg(
+            Arg::from_usage("--poke [ADDR=VAL]... 'Patch memory before running'")
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::from_usage("--input [LIST]... 'Comma-separated input values'").number_of_values(1),
+        )
+        .arg(
+            Arg::from_usage("--ascii-input [FILE]... 'Text file to give as input, - for stdin'")
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::from_usage("--output [MODE] 'How to print output values'")
+                .possible_values(&["numeric", "ascii", "mixed"])
+                .default_value("numeric"),
+        )
+        .arg_from_usage(
+            "--interactive 'Read further input lines from the terminal, printing output as with --output mixed'",
+        )
+        .arg_from_usage("--show-halt 'Print the value at address 0 once the program halts'")
+        .arg_from_usage("--max-instructions [N] 'Stop after executing N instructions'")
+        .arg_from_usage("--max-memory [N] 'Stop before writing at or beyond address N'")
+        .arg_from_usage("--timeout [SECONDS] 'Stop after running this long'")
+        .arg_from_usage("<FILE> 'Program to run'")
+        .after_help(EXIT_CODES)
+        .get_matches();
+    let path = matches.value_of("FILE").unwrap();
+    let memory = File::open(path)
+        .and_then(|file| intcode::read_program(BufReader::new(file)))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let mut machine = Machine::new(&memory);
+    if let Some(n) = matches.value_of("max-instructions") {
+        machine.limits.instructions = Some(n.parse()?);
+    }
+    if let Some(n) = matches.value_of("max-memory") {
+        machine.limits.memory = Some(n.parse()?);
+    }
+    if let Some(seconds) = matches.value_of("timeout") {
+        machine.limits.deadline = Some(deadline(seconds)?);
+    }
+    cli::poke(&mut machine, &matches)?;
+    machine.input.extend(cli::input(&matches)?);
+    let format = match matches.value_of("output") {
+        Some("ascii") => Format::Ascii,
+        Some("mixed") => Format::Mixed,
+        _ => Format::Numeric,
+    };
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let result = if matches.is_present("interactive") {
+        let stdin = io::stdin();
+        let mut ascii = AsciiMachine::from(machine);
+        let result = ascii.interact(stdin.lock(), &mut out);
+        machine = ascii.into_machine();
+        result.map(|event| match event {
+            Event::Halt => Output::Halt(machine.get(0)),
+            _ => Output::NeedsInput,
+        })
+    } else {
+        loop {
+            match machine.run() {
+                Ok(Output::Value(v)) if printable(format, v) => write_value(&mut out, format, v)?,
+                result => break result,
+            }
+        }
+    };
+    let code = match result {
+        Ok(Output::Halt(v)) => {
+            if matches.is_present("show-halt") {
+                if format != Format::Numeric {
+                    writeln!(out)?;
+                }
+                writeln!(out, "{}", v)?;
+            }
+            HALTED
+        }
+        Ok(Output::Value(v)) => {
+            eprintln!("output {} is not a character", v);
+            FAULT
+        }
+        Ok(Output::NeedsInput) => {
+            eprintln!("program needs more input at ip {}", machine.ip);
+            STARVED
+        }
+        Err(e @ IntcodeError::BudgetExhausted { .. }) => {
+            eprintln!("{}", e);
+            EXHAUSTED
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            FAULT
+        }
+    };
+    out.flush()?;
+    process::exit(code);
+}
@@ -0,0 +1,132 @@
+extern crate clap;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use intcode::trace::{self, Divergence, Recorder};
+use intcode::{cli, Machine, Output};
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::process;
+
+fn load(matches: &ArgMatches) -> Result<Machine, Box<dyn Error>> {
+    let path = matches.value_of("FILE").unwrap();
+    let memory = intcode::read_program(BufReader::new(File::open(path)?))?;
+    let mut machine = Machine::new(&memory);
+    cli::poke(&mut machine, matches)?;
+    Ok(machine)
+}
+
+fn open_trace(path: &str) -> io::Result<impl Iterator<Item = io::Result<trace::Event>>> {
+    Ok(trace::read(BufReader::new(File::open(path)?)))
+}
+
+fn record(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let mut machine = load(matches)?;
+    machine.input.extend(cli::input(matches)?);
+    let out: Box<dyn io::Write> = match matches.value_of("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    let mut recorder = Recorder::new(out);
+    loop {
+        match recorder.run(&mut machine)? {
+            Output::Value(v) => eprintln!("output {}", v),
+            Output::NeedsInput => {
+                eprintln!("stopped: program needs more input");
+                break;
+            }
+            Output::Halt(v) => {
+                eprintln!("halt {}", v);
+                break;
+            }
+        }
+    }
+    let events = recorder.events;
+    recorder.finish()?;
+    eprintln!("recorded {} events", events);
+    Ok(())
+}
+
+fn report(divergence: Option<Divergence>, left: &str, right: &str) -> bool {
+    match divergence {
+        None => {
+            println!("no divergence");
+            true
+        }
+        Some(Divergence::Length { index, left_longer }) => {
+            let (longer, shorter) = if left_longer {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            println!(
+                "{} ends after {} events, {} continues",
+                shorter, index, longer
+            );
+            false
+        }
+        Some(Divergence::Event {
+            index,
+            left: l,
+            right: r,
+        }) => {
+            println!("first divergence at event {}", index);
+            println!("{:>8}: {}", left, serde_json::to_string(&l).unwrap());
+            println!("{:>8}: {}", right, serde_json::to_string(&r).unwrap());
+            false
+        }
+    }
+}
+
+fn poke<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::from_usage("--poke [ADDR=VAL]... 'Patch memory before running'").number_of_values(1)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = App::new("INTCODE tracer")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("record")
+                .about("Runs a program and writes a JSON Lines trace")
+                .arg_from_usage("--input [LIST] 'Comma-separated input values'")
+                .arg(poke())
+                .arg_from_usage("-o, --output [TRACE] 'Trace file, stdout if omitted'")
+                .arg_from_usage("<FILE> 'Program to run'"),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Re-executes a program against a recorded trace")
+                .arg(poke())
+                .arg_from_usage("<FILE> 'Program the trace was recorded from'")
+                .arg_from_usage("<TRACE> 'Recorded trace'"),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Finds the first divergence between two traces")
+                .arg_from_usage("<LEFT> 'First trace'")
+                .arg_from_usage("<RIGHT> 'Second trace'"),
+        )
+        .get_matches();
+    let same = match matches.subcommand() {
+        ("record", Some(m)) => return record(m),
+        ("replay", Some(m)) => {
+            let machine = load(m)?;
+            let divergence = trace::replay(machine, open_trace(m.value_of("TRACE").unwrap())?)?;
+            report(divergence, "recorded", "replayed")
+        }
+        ("diff", Some(m)) => {
+            let (left, right) = (m.value_of("LEFT").unwrap(), m.value_of("RIGHT").unwrap());
+            report(
+                trace::diff(open_trace(left)?, open_trace(right)?)?,
+                left,
+                right,
+            )
+        }
+        _ => unreachable!(),
+    };
+    if !same {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,305 @@
+//! An execution engine that decodes each instruction once.
+//!
+//! `Machine::step` splits the opcode into its instruction and parameter
+//! modes, then reads and narrows every parameter word, each time it executes
+//! an instruction. `CachedMachine` does that work once per address: it keeps
+//! an `Op` whose parameters are already resolved to immediate values,
+//! absolute addresses or relative offsets, and executes it with a single
+//! match. A write anywhere inside a cached instruction drops it, so
+//! self-modifying code behaves exactly as under `Machine::run`.
+//!
+//! Instructions that fault whatever the machine state, such as an invalid
+//! opcode or a negative position-mode address, are never cached and go
+//! through `Machine::execute` instead.
+
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::machine::{Executed, Machine, Output};
+use crate::memory::{Memory, VecMemory};
+use crate::word::Word;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+/// Instructions beyond this address are decoded every time rather than
+/// growing the cache to match.
+const CACHE_LIMIT: usize = 1 << 20;
+
+/// Words in the longest instruction, opcode included.
+const MAX_SIZE: usize = 4;
+
+/// A parameter word, read and narrowed ahead of time.
+#[derive(Debug, Clone)]
+enum Param<W> {
+    /// Immediate mode: the value itself.
+    Value(W),
+    /// Position mode: an address known to be valid.
+    At(usize),
+    /// Relative mode: an offset from `rb`, resolved when executed.
+    Relative(i64),
+}
+
+impl<W> Param<W> {
+    fn mode(&self) -> Mode {
+        match self {
+            Param::Value(_) => Mode::Immediate,
+            Param::At(_) => Mode::Position,
+            Param::Relative(_) => Mode::Relative,
+        }
+    }
+}
+
+/// A decoded instruction. Write targets are never `Param::Value`.
+#[derive(Debug, Clone)]
+enum Op<W> {
+    Add(Param<W>, Param<W>, Param<W>),
+    Mul(Param<W>, Param<W>, Param<W>),
+    In(Param<W>),
+    Out(Param<W>),
+    Jnz(Param<W>, Param<W>),
+    Jz(Param<W>, Param<W>),
+    Lt(Param<W>, Param<W>, Param<W>),
+    Eq(Param<W>, Param<W>, Param<W>),
+    Arb(Param<W>),
+    Hlt,
+}
+
+/// Decodes the instruction at `ip`, or returns `None` if executing it
+/// would fault before reaching any state-dependent check.
+fn decode<M: Memory>(machine: &Machine<M>) -> Option<Op<M::Word>> {
+    let Instruction { opcode, modes } = machine.decode().ok()?;
+    let ip = machine.ip;
+    let param = |i: usize| {
+        let word = machine.get(ip + 1 + i);
+        match modes[i] {
+            Mode::Immediate => Some(Param::Value(word)),
+            Mode::Position => usize::try_from(word.to_i64()?).ok().map(Param::At),
+            Mode::Relative => word.to_i64().map(Param::Relative),
+        }
+    };
+    let target = |i: usize| match modes[i] {
+        Mode::Immediate => None,
+        _ => param(i),
+    };
+    Some(match opcode {
+        Opcode::Add => Op::Add(param(0)?, param(1)?, target(2)?),
+        Opcode::Mul => Op::Mul(param(0)?, param(1)?, target(2)?),
+        Opcode::In => Op::In(target(0)?),
+        Opcode::Out => Op::Out(param(0)?),
+        Opcode::Jnz => Op::Jnz(param(0)?, param(1)?),
+        Opcode::Jz => Op::Jz(param(0)?, param(1)?),
+        Opcode::Lt => Op::Lt(param(0)?, param(1)?, target(2)?),
+        Opcode::Eq => Op::Eq(param(0)?, param(1)?, target(2)?),
+        Opcode::Arb => Op::Arb(param(0)?),
+        Opcode::Hlt => Op::Hlt,
+    })
+}
+
+fn address<M: Memory>(machine: &Machine<M>, param: &Param<M::Word>) -> Result<usize, IntcodeError> {
+    match *param {
+        Param::At(address) => Ok(address),
+        Param::Relative(offset) => {
+            let address = offset
+                .checked_add(machine.rb)
+                .ok_or_else(|| machine.overflow())?;
+            machine.check_address(Mode::Relative, address)
+        }
+        Param::Value(_) => unreachable!("write targets are never immediate"),
+    }
+}
+
+fn read<M: Memory>(machine: &Machine<M>, param: &Param<M::Word>) -> Result<M::Word, IntcodeError> {
+    match param {
+        Param::Value(value) => Ok(value.clone()),
+        _ => Ok(machine.get(address(machine, param)?)),
+    }
+}
+
+/// Executes `op` as the instruction at `ip`, faulting where and how
+/// `Machine::execute` would.
+fn execute<M: Memory>(
+    machine: &mut Machine<M>,
+    op: &Op<M::Word>,
+) -> Result<Executed<M::Word>, IntcodeError> {
+    machine.check_budget()?;
+    let executed = apply(machine, op)?;
+    if !matches!(executed.0, Some(Output::NeedsInput)) {
+        machine.executed += 1;
+    }
+    Ok(executed)
+}
+
+fn apply<M: Memory>(
+    machine: &mut Machine<M>,
+    op: &Op<M::Word>,
+) -> Result<Executed<M::Word>, IntcodeError> {
+    match op {
+        Op::Add(a, b, to) | Op::Mul(a, b, to) => {
+            let (a, b) = (read(machine, a)?, read(machine, b)?);
+            let to = address(machine, to)?;
+            let result = match (op, machine.checked) {
+                (Op::Add(..), false) => a.wrapping_add(&b),
+                (_, false) => a.wrapping_mul(&b),
+                (Op::Add(..), true) => a.checked_add(&b).ok_or_else(|| machine.overflow())?,
+                (_, true) => a.checked_mul(&b).ok_or_else(|| machine.overflow())?,
+            };
+            machine.check_write(to)?;
+            machine.set(to, result);
+            machine.ip += 4;
+            Ok((None, Some(to)))
+        }
+        Op::In(to) => {
+            if machine.input.is_empty() {
+                return Ok((Some(Output::NeedsInput), None));
+            }
+            let to = address(machine, to)?;
+            machine.check_write(to)?;
+            let input = machine.input.pop_front().unwrap();
+            machine.set(to, input);
+            machine.ip += 2;
+            Ok((None, Some(to)))
+        }
+        Op::Out(value) => {
+            let value = read(machine, value)?;
+            machine.ip += 2;
+            Ok((Some(Output::Value(value)), None))
+        }
+        Op::Jnz(cond, to) | Op::Jz(cond, to) => {
+            let taken = read(machine, cond)?.is_zero() == matches!(op, Op::Jz(..));
+            let destination = read(machine, to)?;
+            if taken {
+                let destination = machine.narrow(&destination)?;
+                machine.ip = machine.check_address(to.mode(), destination)?;
+            } else {
+                machine.ip += 3;
+            }
+            Ok((None, None))
+        }
+        Op::Lt(a, b, to) | Op::Eq(a, b, to) => {
+            let (a, b) = (read(machine, a)?, read(machine, b)?);
+            let to = address(machine, to)?;
+            machine.check_write(to)?;
+            let holds = match op {
+                Op::Lt(..) => a < b,
+                _ => a == b,
+            };
+            machine.set(to, M::Word::from_i64(holds as i64));
+            machine.ip += 4;
+            Ok((None, Some(to)))
+        }
+        Op::Arb(offset) => {
+            let offset = read(machine, offset)?;
+            let offset = machine.narrow(&offset)?;
+            machine.rb = match machine.checked {
+                false => machine.rb.wrapping_add(offset),
+                true => machine
+                    .rb
+                    .checked_add(offset)
+                    .ok_or_else(|| machine.overflow())?,
+            };
+            machine.ip += 2;
+            Ok((None, None))
+        }
+        Op::Hlt => Ok((Some(Output::Halt(machine.get(0))), None)),
+    }
+}
+
+/// A machine whose decoded instructions are cached.
+///
+/// Memory is only written through `set` or by the program itself, so no
+/// write can go unnoticed by the cache.
+#[derive(Debug, Clone)]
+pub struct CachedMachine<M: Memory = VecMemory> {
+    machine: Machine<M>,
+    cache: Vec<Option<Op<M::Word>>>,
+}
+
+impl CachedMachine {
+    pub fn new(memory: &[i64]) -> CachedMachine {
+        CachedMachine::from(Machine::new(memory))
+    }
+}
+
+impl<M: Memory> From<Machine<M>> for CachedMachine<M> {
+    fn from(machine: Machine<M>) -> CachedMachine<M> {
+        CachedMachine {
+            machine,
+            cache: vec![],
+        }
+    }
+}
+
+impl<M: Memory> CachedMachine<M> {
+    /// The machine state, for inspecting ip, rb or memory.
+    pub fn machine(&self) -> &Machine<M> {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> Machine<M> {
+        self.machine
+    }
+
+    /// Values waiting to be read by opcode 3, front first.
+    pub fn input(&mut self) -> &mut VecDeque<M::Word> {
+        &mut self.machine.input
+    }
+
+    pub fn get(&self, i: usize) -> M::Word {
+        self.machine.get(i)
+    }
+
+    pub fn set(&mut self, i: usize, v: M::Word) {
+        self.machine.set(i, v);
+        self.invalidate(i);
+    }
+
+    /// Drops every cached instruction that covers address `i`.
+    fn invalidate(&mut self, i: usize) {
+        for start in i.saturating_sub(MAX_SIZE - 1)..=i {
+            if let Some(entry) = self.cache.get_mut(start) {
+                *entry = None;
+            }
+        }
+    }
+
+    /// Decodes the instruction at `ip`, caching it if it can be, and
+    /// executes it.
+    fn miss(&mut self) -> Result<Executed<M::Word>, IntcodeError> {
+        let ip = self.machine.ip;
+        let op = match decode(&self.machine) {
+            Some(op) => op,
+            None => {
+                let instruction = self.machine.decode()?;
+                return self.machine.execute(instruction);
+            }
+        };
+        let executed = execute(&mut self.machine, &op);
+        if ip < CACHE_LIMIT {
+            if ip >= self.cache.len() {
+                self.cache.resize(ip + 1, None);
+            }
+            self.cache[ip] = Some(op);
+        }
+        executed
+    }
+
+    /// Like `Machine::step`.
+    pub fn step(&mut self) -> Result<Option<Output<M::Word>>, IntcodeError> {
+        let (output, written) = match self.cache.get(self.machine.ip) {
+            Some(Some(op)) => execute(&mut self.machine, op)?,
+            _ => self.miss()?,
+        };
+        if let Some(i) = written {
+            self.invalidate(i);
+        }
+        Ok(output)
+    }
+
+    /// Like `Machine::run`.
+    pub fn run(&mut self) -> Result<Output<M::Word>, IntcodeError> {
+        loop {
+            if let Some(output) = self.step()? {
+                return Ok(output);
+            }
+        }
+    }
+}
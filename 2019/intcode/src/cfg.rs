@@ -0,0 +1,328 @@
+//! The control-flow graph of a program, for drawing with Graphviz.
+//!
+//! Blocks are built from the instructions `disasm::reachability` finds, so
+//! the graph shows the program as loaded; code it writes at runtime is not
+//! seen. Jumps through memory cannot be followed statically, but compiled
+//! Intcode uses them for one idiom only: a call pushes its return address
+//! with `add #R, #0, [rb+N]` and jumps, and the callee returns with
+//! `jz #0, [rb+0]` once it has moved `rb` back. Calls are recognised that
+//! way, and every return is linked to the return addresses of the calls
+//! into its function.
+
+use crate::disasm::{self, Kind, Line};
+use crate::instruction::{Instruction, Mode, Opcode};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    /// Falling through to the next instruction.
+    Next,
+    /// A taken jump.
+    Jump,
+    /// From a call to the function called.
+    Call,
+    /// From a call to where the function returns to.
+    Resume,
+    /// From a return to where the calls into its function resume.
+    Return,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// How control leaves a block.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exit {
+    /// Runs into the next block, or into data.
+    Next,
+    /// An unconditional jump.
+    Jump,
+    /// A conditional jump.
+    Branch,
+    Call,
+    /// A jump through `[rb+N]`.
+    Return,
+    /// A jump through a fixed address, which cannot be followed.
+    Indirect,
+    Halt,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Block {
+    pub start: usize,
+    /// The address after the last instruction.
+    pub end: usize,
+    pub lines: Vec<Line>,
+    pub exit: Exit,
+}
+
+/// The blocks reached from `entry` without following calls or returns.
+#[derive(Debug, Clone, Serialize)]
+pub struct Function {
+    pub entry: usize,
+    pub blocks: BTreeSet<usize>,
+    /// Blocks calling this function.
+    pub callers: BTreeSet<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+    pub edges: BTreeSet<Edge>,
+    /// The function at address 0 first, then the others by entry.
+    pub functions: Vec<Function>,
+}
+
+/// Splits `program` into basic blocks and functions.
+pub fn build(program: &[i64]) -> Cfg {
+    let listing = disasm::disassemble(program);
+    let mut blocks: Vec<Block> = vec![];
+    for line in listing.into_iter().filter(|line| line.kind == Kind::Code) {
+        let end = line.address + line.words.len();
+        match blocks.last_mut() {
+            Some(block)
+                if block.end == line.address && block.exit == Exit::Next && !line.target =>
+            {
+                block.end = end;
+                block.lines.push(line);
+            }
+            _ => blocks.push(Block {
+                start: line.address,
+                end,
+                lines: vec![line],
+                exit: Exit::Next,
+            }),
+        }
+        let block = blocks.last_mut().unwrap();
+        block.exit = exit(block);
+    }
+
+    let starts = blocks.iter().map(|b| b.start).collect::<BTreeSet<_>>();
+    let mut edges = BTreeSet::new();
+    let mut calls = vec![];
+    for block in &blocks {
+        let last = block.lines.last().unwrap();
+        let from = block.start;
+        if falls_through(last) && starts.contains(&block.end) {
+            edges.insert(Edge {
+                from,
+                to: block.end,
+                kind: EdgeKind::Next,
+            });
+        }
+        if let Some(to) = jump_target(last).filter(|to| starts.contains(to)) {
+            let kind = if block.exit == Exit::Call {
+                EdgeKind::Call
+            } else {
+                EdgeKind::Jump
+            };
+            edges.insert(Edge { from, to, kind });
+        }
+        if block.exit == Exit::Call {
+            if let Some(to) = return_address(block).filter(|to| starts.contains(to)) {
+                edges.insert(Edge {
+                    from,
+                    to,
+                    kind: EdgeKind::Resume,
+                });
+                calls.extend(jump_target(last).map(|entry| (from, entry, to)));
+            }
+        }
+    }
+
+    let mut entries = vec![0];
+    entries.extend(
+        calls
+            .iter()
+            .map(|&(_, entry, _)| entry)
+            .filter(|&entry| entry != 0)
+            .collect::<BTreeSet<_>>(),
+    );
+    let exits = blocks
+        .iter()
+        .map(|b| (b.start, b.exit))
+        .collect::<BTreeMap<_, _>>();
+    let functions = entries
+        .into_iter()
+        .filter(|entry| starts.contains(entry))
+        .map(|entry| {
+            let blocks = body(entry, &edges);
+            let callers = calls
+                .iter()
+                .filter(|&&(_, to, _)| to == entry)
+                .map(|&(from, _, _)| from)
+                .collect();
+            Function {
+                entry,
+                blocks,
+                callers,
+            }
+        })
+        .collect::<Vec<_>>();
+    for &(_, entry, resume) in &calls {
+        let function = functions.iter().find(|f| f.entry == entry);
+        for &from in function.into_iter().flat_map(|f| &f.blocks) {
+            if exits[&from] == Exit::Return {
+                edges.insert(Edge {
+                    from,
+                    to: resume,
+                    kind: EdgeKind::Return,
+                });
+            }
+        }
+    }
+    Cfg {
+        blocks,
+        edges,
+        functions,
+    }
+}
+
+fn decode(line: &Line) -> Instruction {
+    Instruction::decode(line.words[0]).unwrap()
+}
+
+/// Whether the jump in `line` is taken always (`Some(true)`), never
+/// (`Some(false)`) or depending on its condition.
+fn taken(line: &Line) -> Option<bool> {
+    let instruction = decode(line);
+    if instruction.modes[0] != Mode::Immediate {
+        return None;
+    }
+    Some((line.words[1] != 0) == (instruction.opcode == Opcode::Jnz))
+}
+
+fn falls_through(line: &Line) -> bool {
+    match decode(line).opcode {
+        Opcode::Jnz | Opcode::Jz => taken(line) != Some(true),
+        Opcode::Hlt => false,
+        _ => true,
+    }
+}
+
+fn jump_target(line: &Line) -> Option<usize> {
+    let instruction = decode(line);
+    match instruction.opcode {
+        Opcode::Jnz | Opcode::Jz
+            if instruction.modes[1] == Mode::Immediate
+                && line.words[2] >= 0
+                && taken(line) != Some(false) =>
+        {
+            Some(line.words[2] as usize)
+        }
+        _ => None,
+    }
+}
+
+fn exit(block: &Block) -> Exit {
+    let last = block.lines.last().unwrap();
+    let instruction = decode(last);
+    match instruction.opcode {
+        Opcode::Hlt => Exit::Halt,
+        Opcode::Jnz | Opcode::Jz if taken(last) == Some(false) => Exit::Next,
+        Opcode::Jnz | Opcode::Jz => match instruction.modes[1] {
+            Mode::Relative => Exit::Return,
+            Mode::Position => Exit::Indirect,
+            Mode::Immediate if taken(last).is_none() => Exit::Branch,
+            Mode::Immediate if return_address(block).is_some() => Exit::Call,
+            Mode::Immediate => Exit::Jump,
+        },
+        _ => Exit::Next,
+    }
+}
+
+/// The constant last pushed onto the stack in `block`, if it is the
+/// address of an instruction after the block's final jump.
+fn return_address(block: &Block) -> Option<usize> {
+    let value = block.lines.iter().rev().skip(1).find_map(|line| {
+        let instruction = decode(line);
+        let pushes = matches!(instruction.opcode, Opcode::Add | Opcode::Mul)
+            && instruction.modes == [Mode::Immediate, Mode::Immediate, Mode::Relative];
+        if !pushes {
+            return None;
+        }
+        let (a, b) = (line.words[1], line.words[2]);
+        if instruction.opcode == Opcode::Add {
+            a.checked_add(b)
+        } else {
+            a.checked_mul(b)
+        }
+    })?;
+    if value >= block.end as i64 {
+        Some(value as usize)
+    } else {
+        None
+    }
+}
+
+fn body(entry: usize, edges: &BTreeSet<Edge>) -> BTreeSet<usize> {
+    let mut blocks = BTreeSet::new();
+    let mut pending = vec![entry];
+    while let Some(block) = pending.pop() {
+        if !blocks.insert(block) {
+            continue;
+        }
+        let from = Edge {
+            from: block,
+            to: 0,
+            kind: EdgeKind::Next,
+        };
+        pending.extend(
+            edges
+                .range(from..)
+                .take_while(|e| e.from == block)
+                .filter(|e| matches!(e.kind, EdgeKind::Next | EdgeKind::Jump | EdgeKind::Resume))
+                .map(|e| e.to),
+        );
+    }
+    blocks
+}
+
+impl Cfg {
+    /// Renders the graph in Graphviz DOT, one cluster per function.
+    ///
+    /// A block shared between functions is drawn in the first of them.
+    pub fn to_dot(&self) -> String {
+        let mut placed = BTreeSet::new();
+        let mut out = String::from("digraph cfg {\n");
+        out += "    node [shape=box, fontname=\"monospace\"];\n";
+        for function in &self.functions {
+            writeln!(out, "    subgraph cluster_{} {{", function.entry).unwrap();
+            writeln!(out, "        label=\"f{}\";", function.entry).unwrap();
+            for &start in &function.blocks {
+                if placed.insert(start) {
+                    writeln!(out, "        b{};", start).unwrap();
+                }
+            }
+            out += "    }\n";
+        }
+        for block in &self.blocks {
+            let label = block
+                .lines
+                .iter()
+                .map(|line| format!("{:6}  {}\\l", line.address, line.text))
+                .collect::<String>();
+            writeln!(out, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Next => "",
+                EdgeKind::Jump => " [color=blue]",
+                EdgeKind::Call => " [style=bold]",
+                EdgeKind::Resume => " [style=dotted]",
+                EdgeKind::Return => " [style=dashed]",
+            };
+            writeln!(out, "    b{} -> b{}{};", edge.from, edge.to, style).unwrap();
+        }
+        out += "}\n";
+        out
+    }
+}
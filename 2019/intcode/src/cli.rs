@@ -0,0 +1,93 @@
+//! Option handling shared by the command line tools in `src/bin`.
+//!
+//! The tools patch memory with `--poke ADDR=VAL` and give input with any of
+//! `--input LIST`, `--ascii TEXT` and `--ascii-input FILE`, each defining the
+//! options that make sense for it.
+
+use crate::machine::Machine;
+use clap::ArgMatches;
+use std::fs;
+use std::io::{self, Read};
+
+/// Parses `ADDR=VAL`.
+pub fn parse_poke(text: &str) -> Result<(usize, i64), String> {
+    let mut parts = text.splitn(2, '=');
+    let address = parts.next().unwrap().trim();
+    let value = parts
+        .next()
+        .ok_or_else(|| format!("expected ADDR=VAL, found `{}`", text))?
+        .trim();
+    let address = address
+        .parse()
+        .map_err(|_| format!("expected an address, found `{}`", address))?;
+    let value = value
+        .parse()
+        .map_err(|_| format!("expected a number, found `{}`", value))?;
+    Ok((address, value))
+}
+
+/// Parses comma-separated values.
+pub fn parse_list(text: &str) -> Result<Vec<i64>, String> {
+    text.split(',')
+        .map(str::trim)
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("expected a number, found `{}`", v))
+        })
+        .collect()
+}
+
+/// Applies every `--poke`, returning the addresses written.
+pub fn poke(machine: &mut Machine, matches: &ArgMatches) -> Result<Vec<usize>, String> {
+    let mut addresses = vec![];
+    for text in matches.values_of("poke").into_iter().flatten() {
+        let (address, value) = parse_poke(text)?;
+        if let Some(limit) = machine.limits.memory {
+            if address >= limit {
+                return Err(format!(
+                    "poke at {} is beyond the memory limit of {}",
+                    address, limit
+                ));
+            }
+        }
+        machine.set(address, value);
+        addresses.push(address);
+    }
+    Ok(addresses)
+}
+
+/// The input given by `--input`, `--ascii` and `--ascii-input`, in command
+/// line order.
+///
+/// `--ascii` text may use `\n` for a newline. `--ascii-input -` reads stdin.
+pub fn input(matches: &ArgMatches) -> Result<Vec<i64>, String> {
+    let mut parts = vec![];
+    for (index, list) in occurrences(matches, "input") {
+        parts.push((index, parse_list(list)?));
+    }
+    for (index, text) in occurrences(matches, "ascii") {
+        parts.push((index, ascii(&text.replace("\\n", "\n"))));
+    }
+    for (index, path) in occurrences(matches, "ascii-input") {
+        let mut text = String::new();
+        let result = match path {
+            "-" => io::stdin().read_to_string(&mut text).map(|_| ()),
+            path => fs::read_to_string(path).map(|t| text = t),
+        };
+        result.map_err(|e| format!("{}: {}", path, e))?;
+        parts.push((index, ascii(&text)));
+    }
+    parts.sort_by_key(|&(index, _)| index);
+    Ok(parts.into_iter().flat_map(|(_, values)| values).collect())
+}
+
+fn occurrences<'a>(matches: &'a ArgMatches, name: &str) -> Vec<(usize, &'a str)> {
+    match (matches.indices_of(name), matches.values_of(name)) {
+        (Some(indices), Some(values)) => indices.zip(values).collect(),
+        _ => vec![],
+    }
+}
+
+fn ascii(text: &str) -> Vec<i64> {
+    text.bytes().map(i64::from).collect()
+}
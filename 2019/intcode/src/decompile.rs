@@ -0,0 +1,1079 @@
+//! A decompiler from Intcode to structured pseudo-code.
+//!
+//! Works on the blocks and functions found by `cfg::build`. Within each
+//! function the relative base is followed from the entry, so `[rb+N]`
+//! becomes a named slot of the function's frame: `ret` for the return
+//! address, `a1`, `a2`, ... for the parameters the callers pass, `l3`, ...
+//! for locals and `arg1`, ... for the arguments of the calls it makes.
+//! Compiled Intcode returns values by writing over its parameters, so after
+//! a call `argN` reads back what the callee left there. Fixed addresses used
+//! more than once are named `gN`, and a comparison whose result is only ever
+//! tested by the jump right after it is folded into that jump's condition.
+//!
+//! Forward conditional jumps over a single-entry region become `if`, with an
+//! `else` when the region ends by jumping over a second one, and backward
+//! jumps to the head of a single-entry region become `loop` or
+//! `do ... while`. Anything else is left as `goto`.
+//!
+//! `Decompiled::recompile` lowers the structured form back to Intcode,
+//! working out every jump target and pushed return address from the
+//! structure rather than copying them, so comparing the result with the
+//! original program checks the structure that was recovered.
+
+use crate::cfg::{self, Block, Edge, EdgeKind, Exit};
+use crate::instruction::{Instruction, Mode, Opcode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Fixed addresses used at least this many times get a name.
+const NAMED_USES: usize = 2;
+
+/// A single instruction of the original program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Op {
+    pub address: usize,
+    pub instruction: Instruction,
+    pub params: Vec<i64>,
+    /// How far `rb` has moved since the function was entered, if known.
+    pub delta: Option<i64>,
+}
+
+impl Op {
+    fn end(&self) -> usize {
+        self.address + self.instruction.size()
+    }
+
+    fn opcode(&self) -> Opcode {
+        self.instruction.opcode
+    }
+
+    fn immediate(&self, i: usize) -> Option<i64> {
+        match self.instruction.modes[i] {
+            Mode::Immediate => Some(self.params[i]),
+            _ => None,
+        }
+    }
+
+    /// The slot of the function's frame this instruction writes, if any.
+    fn written_slot(&self) -> Option<i64> {
+        let i = self.opcode().write_param()?;
+        match self.instruction.modes[i] {
+            Mode::Relative => self.delta?.checked_add(self.params[i]),
+            _ => None,
+        }
+    }
+
+    /// The slots of the function's frame this instruction reads.
+    fn read_slots(&self) -> impl Iterator<Item = i64> + '_ {
+        let write = self.opcode().write_param();
+        (0..self.opcode().params())
+            .filter(move |&i| Some(i) != write && self.instruction.modes[i] == Mode::Relative)
+            .filter_map(move |i| self.delta?.checked_add(self.params[i]))
+    }
+
+    /// The constant an `add` or `mul` of two immediates computes.
+    fn constant(&self) -> Option<i64> {
+        let (a, b) = (self.immediate(0)?, self.immediate(1)?);
+        match self.opcode() {
+            Opcode::Add => a.checked_add(b),
+            Opcode::Mul => a.checked_mul(b),
+            _ => None,
+        }
+    }
+
+    /// Whether a jump is taken always (`Some(true)`), never (`Some(false)`)
+    /// or depending on its condition.
+    fn taken(&self) -> Option<bool> {
+        self.immediate(0)
+            .map(|condition| (condition != 0) == (self.opcode() == Opcode::Jnz))
+    }
+
+    fn retarget(&self, target: usize) -> Op {
+        let mut op = self.clone();
+        op.params[1] = target as i64;
+        op
+    }
+
+    /// The same constant `add` or `mul`, computing `value` instead. A `mul`
+    /// keeps a factor dividing `value`, and becomes `1 * value` if neither
+    /// does.
+    fn with_constant(&self, value: i64) -> Op {
+        let mut op = self.clone();
+        match (self.opcode(), self.params[0], self.params[1]) {
+            (Opcode::Add, a, _) => op.params[1] = value - a,
+            (_, a, _) if a != 0 && value % a == 0 => op.params[1] = value / a,
+            (_, _, b) if b != 0 && value % b == 0 => op.params[0] = value / b,
+            _ => op.params[..2].copy_from_slice(&[1, value]),
+        }
+        op
+    }
+
+    fn write(&self, out: &mut Vec<i64>) {
+        let end = self.end();
+        if out.len() < end {
+            out.resize(end, 0);
+        }
+        out[self.address] = self.instruction.encode();
+        out[self.address + 1..end].copy_from_slice(&self.params);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Op(Op),
+    Halt(Op),
+    /// A jump that is not part of any structure, with the comparison its
+    /// condition was folded from.
+    Jump {
+        compare: Option<Op>,
+        jump: Op,
+    },
+    /// `then` runs when `branch` is not taken; `otherwise` is the jump
+    /// ending `then` and the code it skips.
+    If {
+        compare: Option<Op>,
+        branch: Op,
+        then: Vec<Stmt>,
+        otherwise: Option<(Op, Vec<Stmt>)>,
+    },
+    /// `body` repeats for as long as `back` jumps to its start.
+    Loop {
+        body: Vec<Stmt>,
+        compare: Option<Op>,
+        back: Op,
+    },
+    /// The stores of the arguments and return address, `setup[push]` being
+    /// the latter, then the jump to the function.
+    Call {
+        setup: Vec<Op>,
+        push: usize,
+        jump: Op,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub entry: usize,
+    /// Slots between `rb` at entry and `rb` once the prologue is done.
+    pub frame: i64,
+    /// Arguments passed by the callers that pass the most.
+    pub params: i64,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Decompiled {
+    image: Vec<i64>,
+    /// Addresses inside reachable instructions.
+    code: BTreeSet<usize>,
+    /// Addresses inside the instructions of some function, which is the code
+    /// `recompile` lowers.
+    lowered: BTreeSet<usize>,
+    /// Parameters of reachable instructions the program writes to.
+    patched: BTreeSet<usize>,
+    /// Addresses only used by comparisons folded into jumps.
+    scratch: BTreeSet<usize>,
+    /// Reads and writes of each fixed address by reachable instructions.
+    pub uses: BTreeMap<usize, usize>,
+    pub functions: Vec<Function>,
+}
+
+/// A basic block within one function, with `rb` tracked through it.
+#[derive(Debug, Clone)]
+struct Piece {
+    start: usize,
+    end: usize,
+    ops: Vec<Op>,
+    exit: Exit,
+}
+
+/// Decompiles `program`.
+pub fn decompile(program: &[i64]) -> Decompiled {
+    let graph = cfg::build(program);
+    let blocks = graph
+        .blocks
+        .iter()
+        .map(|block| (block.start, block))
+        .collect::<BTreeMap<_, _>>();
+    let mut preds = BTreeMap::<usize, BTreeSet<usize>>::new();
+    for edge in &graph.edges {
+        if matches!(
+            edge.kind,
+            EdgeKind::Next | EdgeKind::Jump | EdgeKind::Resume
+        ) {
+            preds.entry(edge.to).or_default().insert(edge.from);
+        }
+    }
+    let all = graph
+        .blocks
+        .iter()
+        .map(|block| ops(block, None))
+        .collect::<Vec<_>>();
+    let scratch = scratch(&all);
+    let code = graph
+        .blocks
+        .iter()
+        .flat_map(|b| b.start..b.end)
+        .collect::<BTreeSet<_>>();
+    let lowered = graph
+        .functions
+        .iter()
+        .flat_map(|function| &function.blocks)
+        .flat_map(|start| blocks[start].start..blocks[start].end)
+        .collect::<BTreeSet<_>>();
+    let mut uses = BTreeMap::new();
+    let mut patched = BTreeSet::new();
+    for op in all.iter().flatten() {
+        for i in 0..op.opcode().params() {
+            if op.instruction.modes[i] != Mode::Position || op.params[i] < 0 {
+                continue;
+            }
+            let address = op.params[i] as usize;
+            *uses.entry(address).or_insert(0) += 1;
+            if op.opcode().write_param() == Some(i) && code.contains(&address) {
+                patched.insert(address);
+            }
+        }
+    }
+
+    let mut functions = graph
+        .functions
+        .iter()
+        .map(|function| {
+            let pieces = pieces(&blocks, &graph.edges, function);
+            let frame = pieces
+                .iter()
+                .flat_map(|piece| &piece.ops)
+                .filter_map(|op| op.delta)
+                .max()
+                .unwrap_or(0)
+                .max(0);
+            let lifter = Lifter {
+                preds: &preds,
+                scratch: &scratch,
+                frame,
+            };
+            Function {
+                entry: function.entry,
+                frame,
+                params: 0,
+                body: lifter.structure(&pieces, true),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut params = BTreeMap::new();
+    for function in &functions {
+        visit(&function.body, &mut |stmt| {
+            if let Stmt::Call { setup, jump, .. } = stmt {
+                let passed = setup
+                    .iter()
+                    .filter_map(|op| op.written_slot())
+                    .map(|slot| slot - function.frame)
+                    .max()
+                    .unwrap_or(0);
+                let entry = params.entry(jump.params[1] as usize).or_insert(0);
+                *entry = passed.max(*entry);
+            }
+        });
+    }
+    for function in &mut functions {
+        function.params = params.get(&function.entry).copied().unwrap_or(0);
+    }
+    Decompiled {
+        image: program.to_vec(),
+        code,
+        lowered,
+        patched,
+        scratch,
+        uses,
+        functions,
+    }
+}
+
+fn ops(block: &Block, delta: Option<i64>) -> Vec<Op> {
+    let mut delta = delta;
+    block
+        .lines
+        .iter()
+        .map(|line| {
+            let instruction = Instruction::decode(line.words[0]).unwrap();
+            let op = Op {
+                address: line.address,
+                instruction,
+                params: line.words[1..].to_vec(),
+                delta,
+            };
+            delta = after(&op, delta);
+            op
+        })
+        .collect()
+}
+
+/// The relative base offset after `op` runs.
+fn after(op: &Op, delta: Option<i64>) -> Option<i64> {
+    match op.opcode() {
+        Opcode::Arb => delta?.checked_add(op.immediate(0)?),
+        _ => delta,
+    }
+}
+
+/// Fixed addresses only ever read by a jump testing what the comparison
+/// just before it wrote there.
+fn scratch(blocks: &[Vec<Op>]) -> BTreeSet<usize> {
+    let mut folds = BTreeMap::new();
+    for ops in blocks {
+        for (n, op) in ops.iter().enumerate() {
+            for i in 0..op.opcode().params() {
+                let address = op.params[i];
+                if op.instruction.modes[i] != Mode::Position
+                    || address < 0
+                    || op.opcode().write_param() == Some(i)
+                {
+                    continue;
+                }
+                let folds_into = i == 0
+                    && matches!(op.opcode(), Opcode::Jnz | Opcode::Jz)
+                    && n > 0
+                    && compares_into(&ops[n - 1], address);
+                *folds.entry(address as usize).or_insert(true) &= folds_into;
+            }
+        }
+    }
+    folds
+        .into_iter()
+        .filter(|&(_, folds)| folds)
+        .map(|(address, _)| address)
+        .collect()
+}
+
+fn compares_into(op: &Op, address: i64) -> bool {
+    matches!(op.opcode(), Opcode::Lt | Opcode::Eq)
+        && op.instruction.modes[2] == Mode::Position
+        && op.params[2] == address
+}
+
+/// The blocks of `function` in address order, with `rb` followed from its
+/// entry along the edges that stay within it.
+fn pieces(
+    blocks: &BTreeMap<usize, &Block>,
+    edges: &BTreeSet<Edge>,
+    function: &cfg::Function,
+) -> Vec<Piece> {
+    let mut entry = BTreeMap::new();
+    entry.insert(function.entry, Some(0));
+    let mut pending = vec![function.entry];
+    while let Some(start) = pending.pop() {
+        let delta = ops(blocks[&start], entry[&start])
+            .iter()
+            .fold(entry[&start], |delta, op| after(op, delta));
+        let from = Edge {
+            from: start,
+            to: 0,
+            kind: EdgeKind::Next,
+        };
+        for edge in edges.range(from..).take_while(|e| e.from == start) {
+            let within = matches!(
+                edge.kind,
+                EdgeKind::Next | EdgeKind::Jump | EdgeKind::Resume
+            ) && function.blocks.contains(&edge.to);
+            if !within {
+                continue;
+            }
+            match entry.get(&edge.to) {
+                None => {
+                    entry.insert(edge.to, delta);
+                    pending.push(edge.to);
+                }
+                Some(&Some(known)) if Some(known) != delta => {
+                    entry.insert(edge.to, None);
+                    pending.push(edge.to);
+                }
+                _ => {}
+            }
+        }
+    }
+    function
+        .blocks
+        .iter()
+        .map(|start| {
+            let block = blocks[start];
+            Piece {
+                start: block.start,
+                end: block.end,
+                ops: ops(block, entry.get(start).copied().flatten()),
+                exit: block.exit,
+            }
+        })
+        .collect()
+}
+
+fn follows(piece: &Piece, next: &Piece) -> bool {
+    piece.end == next.start
+}
+
+fn simple(op: Op) -> Stmt {
+    match op.opcode() {
+        Opcode::Hlt => Stmt::Halt(op),
+        Opcode::Jnz | Opcode::Jz => Stmt::Jump {
+            compare: None,
+            jump: op,
+        },
+        _ => Stmt::Op(op),
+    }
+}
+
+/// Calls `f` on every statement, nested ones included.
+fn visit<F: FnMut(&Stmt)>(stmts: &[Stmt], f: &mut F) {
+    for stmt in stmts {
+        f(stmt);
+        match stmt {
+            Stmt::If {
+                then, otherwise, ..
+            } => {
+                visit(then, f);
+                if let Some((_, otherwise)) = otherwise {
+                    visit(otherwise, f);
+                }
+            }
+            Stmt::Loop { body, .. } => visit(body, f),
+            _ => {}
+        }
+    }
+}
+
+/// The instructions of `stmts` in the order they are laid out.
+fn layout<'s>(stmts: &'s [Stmt], out: &mut Vec<&'s Op>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Op(op) | Stmt::Halt(op) => out.push(op),
+            Stmt::Jump { compare, jump } => out.extend(compare.iter().chain(Some(jump))),
+            Stmt::If {
+                compare,
+                branch,
+                then,
+                otherwise,
+            } => {
+                out.extend(compare.iter().chain(Some(branch)));
+                layout(then, out);
+                if let Some((skip, otherwise)) = otherwise {
+                    out.push(skip);
+                    layout(otherwise, out);
+                }
+            }
+            Stmt::Loop {
+                body,
+                compare,
+                back,
+            } => {
+                layout(body, out);
+                out.extend(compare.iter().chain(Some(back)));
+            }
+            Stmt::Call { setup, jump, .. } => out.extend(setup.iter().chain(Some(jump))),
+        }
+    }
+}
+
+fn first(stmts: &[Stmt]) -> Option<usize> {
+    let mut ops = vec![];
+    layout(stmts, &mut ops);
+    ops.first().map(|op| op.address)
+}
+
+fn end(stmts: &[Stmt]) -> Option<usize> {
+    let mut ops = vec![];
+    layout(stmts, &mut ops);
+    ops.last().map(|op| op.end())
+}
+
+struct Lifter<'a> {
+    preds: &'a BTreeMap<usize, BTreeSet<usize>>,
+    scratch: &'a BTreeSet<usize>,
+    frame: i64,
+}
+
+impl Lifter<'_> {
+    fn structure(&self, pieces: &[Piece], loops: bool) -> Vec<Stmt> {
+        let mut out = vec![];
+        let mut i = 0;
+        while i < pieces.len() {
+            if let Some(k) = self.loop_end(pieces, i).filter(|_| loops || i > 0) {
+                let mut body = pieces[i..=k].to_vec();
+                let last = body.last_mut().unwrap();
+                let back = last.ops.pop().unwrap();
+                last.exit = Exit::Next;
+                let compare = self.fold(&mut last.ops, &back);
+                out.push(Stmt::Loop {
+                    body: self.structure(&body, false),
+                    compare,
+                    back,
+                });
+                i = k + 1;
+                continue;
+            }
+            i = self.piece(pieces, i, &mut out);
+        }
+        out
+    }
+
+    /// Lifts `pieces[i]` into `out`, returning the index of the next piece
+    /// left to lift.
+    fn piece(&self, pieces: &[Piece], i: usize, out: &mut Vec<Stmt>) -> usize {
+        let piece = &pieces[i];
+        let mut ops = piece.ops.clone();
+        let last = match piece.exit {
+            Exit::Next => None,
+            _ => ops.pop(),
+        };
+        let last = match last {
+            Some(last) => last,
+            None => {
+                out.extend(ops.into_iter().map(simple));
+                return i + 1;
+            }
+        };
+        match piece.exit {
+            Exit::Call => {
+                let call = self.call(&mut ops, &last);
+                out.extend(ops.into_iter().map(simple));
+                out.push(match call {
+                    Some((setup, push)) => Stmt::Call {
+                        setup,
+                        push,
+                        jump: last,
+                    },
+                    None => simple(last),
+                });
+            }
+            Exit::Branch => {
+                let compare = self.fold(&mut ops, &last);
+                out.extend(ops.into_iter().map(simple));
+                return self.branch(pieces, i, compare, last, out);
+            }
+            Exit::Halt => {
+                out.extend(ops.into_iter().map(simple));
+                out.push(Stmt::Halt(last));
+            }
+            _ => {
+                let compare = self.fold(&mut ops, &last);
+                out.extend(ops.into_iter().map(simple));
+                out.push(Stmt::Jump {
+                    compare,
+                    jump: last,
+                });
+            }
+        }
+        i + 1
+    }
+
+    /// Takes the comparison `jump` tests off the end of `ops`, if nothing
+    /// else reads its result.
+    fn fold(&self, ops: &mut Vec<Op>, jump: &Op) -> Option<Op> {
+        let last = ops.last()?;
+        let folds = jump.instruction.modes[0] == Mode::Position
+            && compares_into(last, jump.params[0])
+            && jump.params[0] >= 0
+            && self.scratch.contains(&(jump.params[0] as usize));
+        if folds {
+            ops.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Takes the argument and return address stores off the end of `ops`,
+    /// returning them with the index of the latter.
+    ///
+    /// A store is only taken if no store after it touches the same slot, so
+    /// each argument can be shown as the expression stored in it.
+    fn call(&self, ops: &mut Vec<Op>, jump: &Op) -> Option<(Vec<Op>, usize)> {
+        let mut touched = BTreeSet::new();
+        let mut start = ops.len();
+        while let Some(op) = start.checked_sub(1).map(|i| &ops[i]) {
+            match op.written_slot() {
+                Some(slot) if slot >= self.frame && !touched.contains(&slot) => {
+                    touched.insert(slot);
+                    touched.extend(op.read_slots());
+                    start -= 1;
+                }
+                _ => break,
+            }
+        }
+        let push = ops[start..]
+            .iter()
+            .rposition(|op| op.written_slot() == Some(self.frame))?;
+        if ops[start + push].constant() != Some(jump.end() as i64) {
+            return None;
+        }
+        Some((ops.split_off(start), push))
+    }
+
+    fn branch(
+        &self,
+        pieces: &[Piece],
+        i: usize,
+        compare: Option<Op>,
+        branch: Op,
+        out: &mut Vec<Stmt>,
+    ) -> usize {
+        let m = match self.region(pieces, i, branch.params[1]) {
+            Some(m) => m,
+            None => {
+                out.push(Stmt::Jump {
+                    compare,
+                    jump: branch,
+                });
+                return i + 1;
+            }
+        };
+        let mut then = pieces[i + 1..m].to_vec();
+        if let Some(n) = self.otherwise(pieces, i, m) {
+            let last = then.last_mut().unwrap();
+            let skip = last.ops.pop().unwrap();
+            last.exit = Exit::Next;
+            out.push(Stmt::If {
+                compare,
+                branch,
+                then: self.structure(&then, true),
+                otherwise: Some((skip, self.structure(&pieces[m..n], true))),
+            });
+            return n;
+        }
+        out.push(Stmt::If {
+            compare,
+            branch,
+            then: self.structure(&then, true),
+            otherwise: None,
+        });
+        m
+    }
+
+    /// The index of the piece at `target` if the pieces after `pieces[i]`
+    /// up to it make a region only entered from `pieces[i]`.
+    fn region(&self, pieces: &[Piece], i: usize, target: i64) -> Option<usize> {
+        let m = pieces.iter().position(|p| p.start as i64 == target)?;
+        if m > i + 1 && self.single_entry(&pieces[i..m]) && follows(&pieces[m - 1], &pieces[m]) {
+            Some(m)
+        } else {
+            None
+        }
+    }
+
+    /// For a region `pieces[i + 1..m]` ending in a jump over `pieces[m..n]`,
+    /// where those are only entered by the branch in `pieces[i]`, `n`.
+    fn otherwise(&self, pieces: &[Piece], i: usize, m: usize) -> Option<usize> {
+        let last = &pieces[m - 1];
+        if last.exit != Exit::Jump {
+            return None;
+        }
+        let target = last.ops.last()?.params[1];
+        let n = pieces.iter().position(|p| p.start as i64 == target)?;
+        let only_branch = self
+            .preds
+            .get(&pieces[m].start)
+            .is_some_and(|preds| preds.len() == 1 && preds.contains(&pieces[i].start));
+        if n > m
+            && only_branch
+            && self.single_entry(&pieces[m..n])
+            && follows(&pieces[n - 1], &pieces[n])
+        {
+            Some(n)
+        } else {
+            None
+        }
+    }
+
+    /// The last piece jumping back to `pieces[i]` such that the pieces in
+    /// between are only entered from each other.
+    fn loop_end(&self, pieces: &[Piece], i: usize) -> Option<usize> {
+        let header = pieces[i].start as i64;
+        (i..pieces.len()).rev().find(|&k| {
+            let jumps_back = matches!(pieces[k].exit, Exit::Jump | Exit::Branch)
+                && pieces[k].ops.last().map(|op| op.params[1]) == Some(header);
+            jumps_back && self.single_entry(&pieces[i..=k])
+        })
+    }
+
+    /// Whether `pieces` follow on from each other and all but the first are
+    /// only entered from within.
+    fn single_entry(&self, pieces: &[Piece]) -> bool {
+        let starts = pieces.iter().map(|p| p.start).collect::<BTreeSet<_>>();
+        pieces.windows(2).all(|w| follows(&w[0], &w[1]))
+            && pieces[1..].iter().all(|p| {
+                self.preds
+                    .get(&p.start)
+                    .is_none_or(|preds| preds.is_subset(&starts))
+            })
+    }
+}
+
+impl Decompiled {
+    /// Lowers the structured form back to a program.
+    ///
+    /// Only words outside every function are taken from the image: the data,
+    /// and any instructions no function reaches. Everything else comes from
+    /// lowering, so code the structured form lost is left as 0.
+    pub fn recompile(&self) -> Vec<i64> {
+        let mut out = self
+            .image
+            .iter()
+            .enumerate()
+            .map(|(address, &word)| {
+                if self.lowered.contains(&address) {
+                    0
+                } else {
+                    word
+                }
+            })
+            .collect();
+        for function in &self.functions {
+            lower(&function.body, &mut out);
+        }
+        out
+    }
+
+    fn global(&self, address: i64) -> String {
+        let named = address >= 0
+            && self.uses.get(&(address as usize)).copied().unwrap_or(0) >= NAMED_USES
+            && !self.code.contains(&(address as usize));
+        if named {
+            format!("g{}", address)
+        } else {
+            format!("mem[{}]", address)
+        }
+    }
+
+    /// Renders the program as pseudo-code.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut named = self
+            .uses
+            .iter()
+            .filter(|&(address, _)| !self.scratch.contains(address))
+            .filter(|&(&address, _)| self.global(address as i64).starts_with('g'))
+            .collect::<Vec<_>>();
+        named.sort_by_key(|&(&address, &uses)| (std::cmp::Reverse(uses), address));
+        if !named.is_empty() {
+            out += "// globals, by how often the code uses them:\n";
+            for (address, uses) in named {
+                writeln!(out, "//   g{}: {}", address, uses).unwrap();
+            }
+        }
+        for function in &self.functions {
+            let mut renderer = Renderer {
+                decompiled: self,
+                function,
+                lines: vec![],
+                pending: vec![],
+                targets: BTreeSet::new(),
+                loops: vec![],
+            };
+            renderer.stmts(&function.body, 1);
+            let params = (1..=function.params)
+                .map(|i| format!("a{}", i))
+                .collect::<Vec<_>>();
+            out += "\n";
+            if function.entry != 0 {
+                writeln!(out, "// frame size {}", function.frame).unwrap();
+            }
+            writeln!(out, "fn {}({}) {{", name(function.entry), params.join(", ")).unwrap();
+            for (addresses, depth, text) in &renderer.lines {
+                if let Some(&target) = addresses.iter().find(|a| renderer.targets.contains(a)) {
+                    writeln!(out, "{}L{}:", "    ".repeat(depth - 1), target).unwrap();
+                }
+                writeln!(out, "{}{}", "    ".repeat(*depth), text).unwrap();
+            }
+            out += "}\n";
+        }
+        out
+    }
+}
+
+fn name(entry: usize) -> String {
+    match entry {
+        0 => "main".to_string(),
+        entry => format!("f{}", entry),
+    }
+}
+
+fn lower(stmts: &[Stmt], out: &mut Vec<i64>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Op(op) | Stmt::Halt(op) => op.write(out),
+            Stmt::Jump { compare, jump } => {
+                compare
+                    .iter()
+                    .chain(Some(jump))
+                    .for_each(|op| op.write(out));
+            }
+            Stmt::If {
+                compare,
+                branch,
+                then,
+                otherwise,
+            } => {
+                if let Some(compare) = compare {
+                    compare.write(out);
+                }
+                match otherwise {
+                    None => branch.retarget(end(then).unwrap()).write(out),
+                    Some((skip, otherwise)) => {
+                        branch.retarget(first(otherwise).unwrap()).write(out);
+                        skip.retarget(end(otherwise).unwrap()).write(out);
+                        lower(otherwise, out);
+                    }
+                }
+                lower(then, out);
+            }
+            Stmt::Loop {
+                body,
+                compare,
+                back,
+            } => {
+                let start = first(std::slice::from_ref(stmt)).unwrap();
+                lower(body, out);
+                if let Some(compare) = compare {
+                    compare.write(out);
+                }
+                back.retarget(start).write(out);
+            }
+            Stmt::Call { setup, push, jump } => {
+                for (i, op) in setup.iter().enumerate() {
+                    if i == *push {
+                        op.with_constant(jump.end() as i64).write(out);
+                    } else {
+                        op.write(out);
+                    }
+                }
+                jump.write(out);
+            }
+        }
+    }
+}
+
+struct Renderer<'a> {
+    decompiled: &'a Decompiled,
+    function: &'a Function,
+    /// Rendered lines with the addresses of the instructions behind them.
+    lines: Vec<(Vec<usize>, usize, String)>,
+    /// Instructions rendered as nothing, credited to the next line.
+    pending: Vec<usize>,
+    /// Addresses `goto` jumps to.
+    targets: BTreeSet<usize>,
+    /// Start and end of each loop being rendered, innermost last.
+    loops: Vec<(usize, usize)>,
+}
+
+impl Renderer<'_> {
+    fn line(&mut self, depth: usize, text: String, ops: &[&Op]) {
+        let mut addresses = std::mem::take(&mut self.pending);
+        addresses.extend(ops.iter().map(|op| op.address));
+        self.lines.push((addresses, depth, text));
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt], depth: usize) {
+        for stmt in stmts {
+            self.stmt(stmt, depth);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, depth: usize) {
+        match stmt {
+            Stmt::Op(op) if op.opcode() == Opcode::Arb && op.delta.is_some() => {
+                self.pending.push(op.address)
+            }
+            Stmt::Op(op) => {
+                let text = format!("{};", self.op(op));
+                self.line(depth, text, &[op]);
+            }
+            Stmt::Halt(op) => self.line(depth, "halt;".to_string(), &[op]),
+            Stmt::Jump { compare, jump } => {
+                let text = self.jump(compare.as_ref(), jump);
+                let ops = compare.iter().chain(Some(jump)).collect::<Vec<_>>();
+                self.line(depth, text, &ops);
+            }
+            Stmt::If {
+                compare,
+                branch,
+                then,
+                otherwise,
+            } => {
+                let mut ops = compare.iter().chain(Some(branch)).collect::<Vec<_>>();
+                if let (true, Some((skip, otherwise))) = (then.is_empty(), otherwise) {
+                    ops.push(skip);
+                    let condition = self.condition(compare.as_ref(), branch, true);
+                    self.line(depth, format!("if ({}) {{", condition), &ops);
+                    self.stmts(otherwise, depth + 1);
+                    self.line(depth, "}".to_string(), &[]);
+                    return;
+                }
+                let condition = self.condition(compare.as_ref(), branch, false);
+                self.line(depth, format!("if ({}) {{", condition), &ops);
+                self.stmts(then, depth + 1);
+                if let Some((skip, otherwise)) = otherwise {
+                    self.line(depth, "} else {".to_string(), &[skip]);
+                    self.stmts(otherwise, depth + 1);
+                }
+                self.line(depth, "}".to_string(), &[]);
+            }
+            Stmt::Loop {
+                body,
+                compare,
+                back,
+            } => {
+                let start = first(std::slice::from_ref(stmt)).unwrap();
+                self.loops.push((start, back.end()));
+                let ops = compare.iter().chain(Some(back)).collect::<Vec<_>>();
+                if back.taken() == Some(true) {
+                    self.line(depth, "loop {".to_string(), &[]);
+                    self.stmts(body, depth + 1);
+                    self.line(depth, "}".to_string(), &ops);
+                } else {
+                    self.line(depth, "do {".to_string(), &[]);
+                    self.stmts(body, depth + 1);
+                    let condition = self.condition(compare.as_ref(), back, true);
+                    self.line(depth, format!("}} while ({});", condition), &ops);
+                }
+                self.loops.pop();
+            }
+            Stmt::Call { setup, jump, .. } => {
+                let callee = jump.params[1];
+                let passed = self
+                    .decompiled
+                    .functions
+                    .iter()
+                    .find(|f| f.entry as i64 == callee)
+                    .map_or(0, |f| f.params);
+                let args = (1..=passed)
+                    .map(|j| {
+                        let slot = self.function.frame + j;
+                        match setup
+                            .iter()
+                            .rev()
+                            .find(|op| op.written_slot() == Some(slot))
+                        {
+                            Some(op) => self.expression(op),
+                            None => self.slot(slot),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let ops = setup.iter().chain(Some(jump)).collect::<Vec<_>>();
+                let text = format!("{}({});", name(callee as usize), args.join(", "));
+                self.line(depth, text, &ops);
+            }
+        }
+    }
+
+    fn op(&self, op: &Op) -> String {
+        match op.opcode() {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq | Opcode::In => {
+                format!(
+                    "{} = {}",
+                    self.operand(op, op.opcode().params() - 1),
+                    self.expression(op)
+                )
+            }
+            Opcode::Out => format!("output({})", self.operand(op, 0)),
+            Opcode::Arb => format!("rb += {}", self.operand(op, 0)),
+            _ => op.instruction.format(&op.params),
+        }
+    }
+
+    /// The value an instruction stores.
+    fn expression(&self, op: &Op) -> String {
+        if op.opcode() == Opcode::In {
+            return "input()".to_string();
+        }
+        let (a, b) = (self.operand(op, 0), self.operand(op, 1));
+        match (op.opcode(), op.immediate(0), op.immediate(1)) {
+            (Opcode::Add, Some(0), _) | (Opcode::Mul, Some(1), _) => b,
+            (Opcode::Add, _, Some(0)) | (Opcode::Mul, _, Some(1)) => a,
+            (Opcode::Add, _, Some(v)) if v < 0 => format!("{} - {}", a, v.unsigned_abs()),
+            (Opcode::Add, Some(v), _) if v < 0 => format!("{} - {}", b, v.unsigned_abs()),
+            (Opcode::Add, _, _) => format!("{} + {}", a, b),
+            (Opcode::Mul, Some(-1), _) => format!("-{}", b),
+            (Opcode::Mul, _, Some(-1)) => format!("-{}", a),
+            (Opcode::Mul, _, _) => format!("{} * {}", a, b),
+            (Opcode::Lt, _, _) => format!("{} < {}", a, b),
+            _ => format!("{} == {}", a, b),
+        }
+    }
+
+    /// The condition under which `jump` is taken, or not taken.
+    fn condition(&self, compare: Option<&Op>, jump: &Op, taken: bool) -> String {
+        let nonzero = (jump.opcode() == Opcode::Jnz) == taken;
+        match compare {
+            Some(compare) => {
+                let relation = match (compare.opcode(), nonzero) {
+                    (Opcode::Lt, true) => "<",
+                    (Opcode::Lt, false) => ">=",
+                    (_, true) => "==",
+                    (_, false) => "!=",
+                };
+                let (a, b) = (self.operand(compare, 0), self.operand(compare, 1));
+                format!("{} {} {}", a, relation, b)
+            }
+            None => {
+                let relation = if nonzero { "!=" } else { "==" };
+                format!("{} {} 0", self.operand(jump, 0), relation)
+            }
+        }
+    }
+
+    fn jump(&mut self, compare: Option<&Op>, jump: &Op) -> String {
+        let action = match jump.instruction.modes[1] {
+            Mode::Relative => "return".to_string(),
+            Mode::Position => format!("goto *{}", self.operand(jump, 1)),
+            Mode::Immediate => {
+                let target = jump.params[1] as usize;
+                match self.loops.last() {
+                    Some(&(start, _)) if start == target => "continue".to_string(),
+                    Some(&(_, end)) if end == target => "break".to_string(),
+                    _ => {
+                        self.targets.insert(target);
+                        format!("goto L{}", target)
+                    }
+                }
+            }
+        };
+        match jump.taken() {
+            Some(true) => format!("{};", action),
+            Some(false) => format!("nop; // {}", jump.instruction.format(&jump.params)),
+            None => format!("if ({}) {};", self.condition(compare, jump, true), action),
+        }
+    }
+
+    fn operand(&self, op: &Op, i: usize) -> String {
+        let word = op.address + 1 + i;
+        if self.decompiled.patched.contains(&word) {
+            let value = format!("mem[{}]", word);
+            return match op.instruction.modes[i] {
+                Mode::Immediate => value,
+                Mode::Position => format!("mem[{}]", value),
+                Mode::Relative => format!("[rb + {}]", value),
+            };
+        }
+        let value = op.params[i];
+        match op.instruction.modes[i] {
+            Mode::Immediate => value.to_string(),
+            Mode::Position => self.decompiled.global(value),
+            Mode::Relative => match op.delta.and_then(|delta| delta.checked_add(value)) {
+                Some(slot) => self.slot(slot),
+                None => format!("[rb{:+}]", value),
+            },
+        }
+    }
+
+    /// The name of a slot of the frame, counted from `rb` at entry.
+    fn slot(&self, slot: i64) -> String {
+        let function = self.function;
+        match slot {
+            _ if function.entry == 0 && slot < function.frame => self.decompiled.global(slot),
+            _ if slot < 0 => format!("[frame{}]", slot),
+            0 if function.entry != 0 => "ret".to_string(),
+            _ if slot <= function.params => format!("a{}", slot),
+            _ if slot < function.frame => format!("l{}", slot),
+            _ => format!("arg{}", slot - function.frame),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::instruction::{Instruction, Mode, Opcode};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Maximum number of data words rendered on a single `db` line.
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Code,
+    Data,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Line {
+    pub address: usize,
+    pub kind: Kind,
+    pub words: Vec<i64>,
+    pub text: String,
+    /// Whether some reachable jump lands on this address.
+    pub target: bool,
+}
+
+/// The result of walking a program from its entry point.
+#[derive(Debug, Default)]
+pub struct Reachability {
+    /// Addresses at which a reachable instruction starts.
+    pub code: BTreeSet<usize>,
+    /// Immediate targets of reachable `jnz`/`jz` instructions.
+    pub targets: BTreeSet<usize>,
+}
+
+impl Reachability {
+    fn covers(&self, program: &[i64], address: usize) -> bool {
+        self.code
+            .range(..=address)
+            .next_back()
+            .is_some_and(|&start| {
+                address < start + Instruction::decode(program[start]).unwrap().size()
+            })
+    }
+}
+
+/// Finds the instructions reachable from address 0.
+///
+/// Control flow is followed through fall-through and immediate jump targets.
+/// Jumps through memory (the `jz #0, [rb+0]` return idiom) cannot be followed
+/// statically, so constants pushed onto the stack with a relative-mode `add` or
+/// `mul` are treated as return addresses and explored once the main walk is done.
+pub fn reachability(program: &[i64]) -> Reachability {
+    let mut reach = Reachability::default();
+    let mut candidates = vec![];
+    walk(program, 0, &mut reach, &mut candidates);
+    while let Some(address) = candidates.pop() {
+        if !reach.covers(program, address) {
+            reach.targets.insert(address);
+            walk(program, address, &mut reach, &mut candidates);
+        }
+    }
+    reach
+}
+
+fn walk(program: &[i64], entry: usize, reach: &mut Reachability, candidates: &mut Vec<usize>) {
+    let mut pending = vec![entry];
+    while let Some(address) = pending.pop() {
+        if reach.covers(program, address) {
+            continue;
+        }
+        let instruction = match program.get(address).map(|&raw| Instruction::decode(raw)) {
+            Some(Ok(instruction)) => instruction,
+            _ => continue,
+        };
+        let end = address + instruction.size();
+        if end > program.len() || (address + 1..end).any(|a| reach.code.contains(&a)) {
+            continue;
+        }
+        reach.code.insert(address);
+        let params = &program[address + 1..end];
+        let modes = instruction.modes;
+        match instruction.opcode {
+            Opcode::Hlt => continue,
+            Opcode::Jnz | Opcode::Jz => {
+                if modes[1] == Mode::Immediate && params[1] >= 0 {
+                    reach.targets.insert(params[1] as usize);
+                    pending.push(params[1] as usize);
+                }
+                let always = modes[0] == Mode::Immediate
+                    && (params[0] != 0) == (instruction.opcode == Opcode::Jnz);
+                if always {
+                    continue;
+                }
+            }
+            Opcode::Add | Opcode::Mul
+                if modes[0] == Mode::Immediate
+                    && modes[1] == Mode::Immediate
+                    && modes[2] == Mode::Relative =>
+            {
+                let value = if instruction.opcode == Opcode::Add {
+                    params[0].checked_add(params[1])
+                } else {
+                    params[0].checked_mul(params[1])
+                };
+                match value {
+                    Some(value) if value >= 0 && (value as usize) < program.len() => {
+                        candidates.push(value as usize)
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        pending.push(end);
+    }
+}
+
+/// Renders the instruction at `address`, reading memory through `read`.
+///
+/// Returns the text and the number of words consumed; words that do not decode
+/// are rendered as a single `db`.
+pub fn describe<F: Fn(usize) -> i64>(read: F, address: usize) -> (String, usize) {
+    match Instruction::decode(read(address)) {
+        Ok(instruction) => {
+            let params = (1..instruction.size())
+                .map(|i| read(address + i))
+                .collect::<Vec<i64>>();
+            (instruction.format(&params), instruction.size())
+        }
+        Err(_) => (format!("db {}", read(address)), 1),
+    }
+}
+
+/// Splits a program into code and data lines.
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let reach = reachability(program);
+    let mut lines: Vec<Line> = vec![];
+    let mut address = 0;
+    while address < program.len() {
+        let target = reach.targets.contains(&address);
+        if reach.code.contains(&address) {
+            let instruction = Instruction::decode(program[address]).unwrap();
+            let words = program[address..address + instruction.size()].to_vec();
+            lines.push(Line {
+                address,
+                kind: Kind::Code,
+                text: instruction.format(&words[1..]),
+                words,
+                target,
+            });
+            address += instruction.size();
+            continue;
+        }
+        match lines.last_mut() {
+            Some(line)
+                if line.kind == Kind::Data && !target && line.words.len() < DATA_PER_LINE =>
+            {
+                line.words.push(program[address]);
+            }
+            _ => lines.push(Line {
+                address,
+                kind: Kind::Data,
+                words: vec![program[address]],
+                text: String::new(),
+                target,
+            }),
+        }
+        address += 1;
+    }
+    for line in lines.iter_mut().filter(|line| line.kind == Kind::Data) {
+        line.text = format!(
+            "db {}",
+            line.words
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    lines
+}
+
+/// Renders a listing as annotated assembly text.
+pub fn render(lines: &[Line]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        if line.target {
+            out += &format!("L{}:\n", line.address);
+        }
+        let raw = match line.kind {
+            Kind::Code => line
+                .words
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+            Kind::Data => String::new(),
+        };
+        out += &format!("{:6}  {:<24}  {}\n", line.address, raw, line.text);
+    }
+    out
+}
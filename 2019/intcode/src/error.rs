@@ -0,0 +1,145 @@
+use crate::limits::Limit;
+use std::error::Error;
+use std::fmt;
+
+/// A fault raised while executing an Intcode program.
+///
+/// Every variant records the instruction pointer and raw opcode of the faulting
+/// instruction, so the caller can tell which program went wrong and where.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntcodeError {
+    /// The low two digits of the opcode do not name an instruction.
+    InvalidOpcode { ip: usize, opcode: i64 },
+    /// A parameter mode digit other than 0, 1 or 2.
+    InvalidMode {
+        ip: usize,
+        opcode: i64,
+        mode: i64,
+        rb: i64,
+    },
+    /// A parameter resolved to a negative memory address.
+    NegativeAddress {
+        ip: usize,
+        opcode: i64,
+        mode: i64,
+        rb: i64,
+        address: i64,
+    },
+    /// A write target was given in immediate mode.
+    ImmediateWrite { ip: usize, opcode: i64, rb: i64 },
+    /// The program asked for input after the queue ran dry, where the caller
+    /// expected it to run to completion.
+    MissingInput { ip: usize, opcode: i64 },
+    /// The program output a value after the sink it writes to had closed.
+    OutputClosed { ip: usize, opcode: i64 },
+    /// The device given to `Machine::run_with` failed to supply input or to
+    /// take output.
+    Device {
+        ip: usize,
+        opcode: i64,
+        message: String,
+    },
+    /// Checked arithmetic overflowed the machine's word, or a value used as
+    /// an address or relative base offset does not fit in an `i64`.
+    Overflow { ip: usize, opcode: i64 },
+    /// The instruction at `ip` would have exceeded one of the machine's
+    /// `limits`; it was not executed.
+    BudgetExhausted {
+        ip: usize,
+        opcode: i64,
+        limit: Limit,
+    },
+}
+
+impl IntcodeError {
+    /// The instruction pointer of the faulting instruction.
+    pub fn ip(&self) -> usize {
+        match *self {
+            IntcodeError::InvalidOpcode { ip, .. }
+            | IntcodeError::InvalidMode { ip, .. }
+            | IntcodeError::NegativeAddress { ip, .. }
+            | IntcodeError::ImmediateWrite { ip, .. }
+            | IntcodeError::MissingInput { ip, .. }
+            | IntcodeError::OutputClosed { ip, .. }
+            | IntcodeError::Device { ip, .. }
+            | IntcodeError::Overflow { ip, .. }
+            | IntcodeError::BudgetExhausted { ip, .. } => ip,
+        }
+    }
+
+    /// The raw opcode of the faulting instruction, parameter modes included.
+    pub fn opcode(&self) -> i64 {
+        match *self {
+            IntcodeError::InvalidOpcode { opcode, .. }
+            | IntcodeError::InvalidMode { opcode, .. }
+            | IntcodeError::NegativeAddress { opcode, .. }
+            | IntcodeError::ImmediateWrite { opcode, .. }
+            | IntcodeError::MissingInput { opcode, .. }
+            | IntcodeError::OutputClosed { opcode, .. }
+            | IntcodeError::Device { opcode, .. }
+            | IntcodeError::Overflow { opcode, .. }
+            | IntcodeError::BudgetExhausted { opcode, .. } => opcode,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntcodeError::InvalidOpcode { ip, opcode } => {
+                write!(f, "invalid opcode {} at ip {}", opcode, ip)
+            }
+            IntcodeError::InvalidMode {
+                ip,
+                opcode,
+                mode,
+                rb,
+            } => write!(
+                f,
+                "invalid parameter mode {} in opcode {} at ip {} (rb {})",
+                mode, opcode, ip, rb
+            ),
+            IntcodeError::NegativeAddress {
+                ip,
+                opcode,
+                mode,
+                rb,
+                address,
+            } => write!(
+                f,
+                "negative address {} (mode {}) in opcode {} at ip {} (rb {})",
+                address, mode, opcode, ip, rb
+            ),
+            IntcodeError::ImmediateWrite { ip, opcode, rb } => write!(
+                f,
+                "immediate mode write target in opcode {} at ip {} (rb {})",
+                opcode, ip, rb
+            ),
+            IntcodeError::MissingInput { ip, opcode } => {
+                write!(f, "out of input in opcode {} at ip {}", opcode, ip)
+            }
+            IntcodeError::OutputClosed { ip, opcode } => {
+                write!(f, "output closed in opcode {} at ip {}", opcode, ip)
+            }
+            IntcodeError::Device {
+                ip,
+                opcode,
+                ref message,
+            } => write!(
+                f,
+                "device error in opcode {} at ip {}: {}",
+                opcode, ip, message
+            ),
+            IntcodeError::Overflow { ip, opcode } => {
+                write!(f, "overflow in opcode {} at ip {}", opcode, ip)
+            }
+            IntcodeError::BudgetExhausted { ip, opcode, limit } => write!(
+                f,
+                "{} budget exhausted before opcode {} at ip {}",
+                limit, opcode, ip
+            ),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Opcode {
+    Add,
+    Mul,
+    In,
+    Out,
+    Jnz,
+    Jz,
+    Lt,
+    Eq,
+    Arb,
+    Hlt,
+}
+
+impl Opcode {
+    pub const ALL: [Opcode; 10] = [
+        Opcode::Add,
+        Opcode::Mul,
+        Opcode::In,
+        Opcode::Out,
+        Opcode::Jnz,
+        Opcode::Jz,
+        Opcode::Lt,
+        Opcode::Eq,
+        Opcode::Arb,
+        Opcode::Hlt,
+    ];
+
+    pub fn from_code(code: i64) -> Option<Opcode> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Mul),
+            3 => Some(Opcode::In),
+            4 => Some(Opcode::Out),
+            5 => Some(Opcode::Jnz),
+            6 => Some(Opcode::Jz),
+            7 => Some(Opcode::Lt),
+            8 => Some(Opcode::Eq),
+            9 => Some(Opcode::Arb),
+            99 => Some(Opcode::Hlt),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::In => 3,
+            Opcode::Out => 4,
+            Opcode::Jnz => 5,
+            Opcode::Jz => 6,
+            Opcode::Lt => 7,
+            Opcode::Eq => 8,
+            Opcode::Arb => 9,
+            Opcode::Hlt => 99,
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        Opcode::ALL
+            .iter()
+            .cloned()
+            .find(|op| op.mnemonic() == mnemonic)
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Mul => "mul",
+            Opcode::In => "in",
+            Opcode::Out => "out",
+            Opcode::Jnz => "jnz",
+            Opcode::Jz => "jz",
+            Opcode::Lt => "lt",
+            Opcode::Eq => "eq",
+            Opcode::Arb => "arb",
+            Opcode::Hlt => "hlt",
+        }
+    }
+
+    /// Number of parameters following the opcode word.
+    pub fn params(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+            Opcode::Jnz | Opcode::Jz => 2,
+            Opcode::In | Opcode::Out | Opcode::Arb => 1,
+            Opcode::Hlt => 0,
+        }
+    }
+
+    /// Index of the parameter the instruction writes to, if any.
+    pub fn write_param(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => Some(2),
+            Opcode::In => Some(0),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.mnemonic())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    pub fn from_digit(digit: i64) -> Option<Mode> {
+        match digit {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn digit(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+
+    /// Renders a parameter as `[addr]`, `#imm` or `[rb+off]`.
+    pub fn format(self, param: i64) -> String {
+        match self {
+            Mode::Position => format!("[{}]", param),
+            Mode::Immediate => format!("#{}", param),
+            Mode::Relative if param < 0 => format!("[rb{}]", param),
+            Mode::Relative => format!("[rb+{}]", param),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeError {
+    InvalidOpcode,
+    InvalidMode(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    pub fn decode(raw: i64) -> Result<Instruction, DecodeError> {
+        let mode = raw / 100;
+        let digits: [i64; 3] = [mode % 10, mode % 100 / 10, mode / 100];
+        let mut modes = [Mode::Position; 3];
+        for (m, &digit) in modes.iter_mut().zip(digits.iter()) {
+            *m = Mode::from_digit(digit).ok_or(DecodeError::InvalidMode(digit))?;
+        }
+        let opcode = Opcode::from_code(raw % 100).ok_or(DecodeError::InvalidOpcode)?;
+        if opcode == Opcode::Hlt && mode != 0 {
+            return Err(DecodeError::InvalidOpcode);
+        }
+        Ok(Instruction { opcode, modes })
+    }
+
+    pub fn encode(&self) -> i64 {
+        self.modes
+            .iter()
+            .rev()
+            .fold(0, |acc, mode| acc * 10 + mode.digit())
+            * 100
+            + self.opcode.code()
+    }
+
+    /// Number of words occupied by the instruction, opcode included.
+    pub fn size(&self) -> usize {
+        self.opcode.params() + 1
+    }
+
+    /// Renders the instruction in assembly syntax given its parameter words.
+    pub fn format(&self, params: &[i64]) -> String {
+        let operands = params
+            .iter()
+            .zip(self.modes.iter())
+            .take(self.opcode.params())
+            .map(|(&param, mode)| mode.format(param))
+            .collect::<Vec<String>>();
+        if operands.is_empty() {
+            self.opcode.mnemonic().to_string()
+        } else {
+            format!("{} {}", self.opcode.mnemonic(), operands.join(", "))
+        }
+    }
+}
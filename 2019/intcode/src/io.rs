@@ -0,0 +1,185 @@
+//! Devices a machine can be wired to with `Machine::run_with`.
+
+use std::collections::VecDeque;
+use std::io;
+use std::io::prelude::*;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Something that feeds a machine its input and consumes its output.
+///
+/// An error from either method stops the machine with
+/// `IntcodeError::Device`.
+pub trait IoDevice {
+    /// The next input value, or `None` if there is none (yet), in which case
+    /// the machine stops with `Output::NeedsInput`.
+    fn read(&mut self) -> io::Result<Option<i64>>;
+    fn write(&mut self, value: i64) -> io::Result<()>;
+}
+
+/// Reads one integer per line and writes one integer per line.
+///
+/// Blank lines are skipped; any other line that is not a number is an
+/// `InvalidData` error.
+pub struct Numeric<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: BufRead, W: Write> Numeric<R, W> {
+    pub fn new(reader: R, writer: W) -> Numeric<R, W> {
+        Numeric { reader, writer }
+    }
+}
+
+impl Numeric<io::StdinLock<'static>, io::Stdout> {
+    pub fn stdio() -> Self {
+        Numeric::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> IoDevice for Numeric<R, W> {
+    fn read(&mut self) -> io::Result<Option<i64>> {
+        self.writer.flush()?;
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            match line.trim() {
+                "" => continue,
+                text => {
+                    return text.parse().map(Some).map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("expected a number, found `{}`", text),
+                        )
+                    })
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, value: i64) -> io::Result<()> {
+        writeln!(self.writer, "{}", value)
+    }
+}
+
+/// Feeds lines of text as character codes and prints output as characters.
+///
+/// Output outside the ASCII range, such as a final answer, is printed as a
+/// number on its own line.
+pub struct Ascii<R, W> {
+    reader: R,
+    writer: W,
+    pending: VecDeque<i64>,
+}
+
+impl<R: BufRead, W: Write> Ascii<R, W> {
+    pub fn new(reader: R, writer: W) -> Ascii<R, W> {
+        Ascii {
+            reader,
+            writer,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl Ascii<io::StdinLock<'static>, io::Stdout> {
+    pub fn stdio() -> Self {
+        Ascii::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> IoDevice for Ascii<R, W> {
+    fn read(&mut self) -> io::Result<Option<i64>> {
+        if self.pending.is_empty() {
+            self.writer.flush()?;
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.ends_with('\n') {
+                line.push('\n');
+            }
+            self.pending.extend(line.bytes().map(i64::from));
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    fn write(&mut self, value: i64) -> io::Result<()> {
+        match value {
+            0..=127 => self.writer.write_all(&[value as u8]),
+            _ => writeln!(self.writer, "{}", value),
+        }
+    }
+}
+
+/// Serves a fixed list of inputs and collects every output.
+#[derive(Debug, Clone, Default)]
+pub struct Fixed {
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+}
+
+impl Fixed {
+    pub fn new<I: IntoIterator<Item = i64>>(input: I) -> Fixed {
+        Fixed {
+            input: input.into_iter().collect(),
+            output: vec![],
+        }
+    }
+}
+
+impl IoDevice for Fixed {
+    fn read(&mut self) -> io::Result<Option<i64>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn write(&mut self, value: i64) -> io::Result<()> {
+        self.output.push(value);
+        Ok(())
+    }
+}
+
+/// Connects a machine to others through channels.
+///
+/// Reads block until a value arrives; a disconnected input reads as `None`
+/// and output sent to a dropped receiver is discarded.
+pub struct Channel {
+    pub input: Receiver<i64>,
+    pub output: Sender<i64>,
+}
+
+impl IoDevice for Channel {
+    fn read(&mut self) -> io::Result<Option<i64>> {
+        Ok(self.input.recv().ok())
+    }
+
+    fn write(&mut self, value: i64) -> io::Result<()> {
+        let _ = self.output.send(value);
+        Ok(())
+    }
+}
+
+/// A device made of a pair of closures.
+pub struct Closures<R, W> {
+    read: R,
+    write: W,
+}
+
+impl<R: FnMut() -> Option<i64>, W: FnMut(i64)> Closures<R, W> {
+    pub fn new(read: R, write: W) -> Closures<R, W> {
+        Closures { read, write }
+    }
+}
+
+impl<R: FnMut() -> Option<i64>, W: FnMut(i64)> IoDevice for Closures<R, W> {
+    fn read(&mut self) -> io::Result<Option<i64>> {
+        Ok((self.read)())
+    }
+
+    fn write(&mut self, value: i64) -> io::Result<()> {
+        (self.write)(value);
+        Ok(())
+    }
+}
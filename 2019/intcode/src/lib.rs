@@ -0,0 +1,50 @@
+pub mod analysis;
+pub mod aot;
+pub mod ascii;
+pub mod asm;
+pub mod cached;
+pub mod cfg;
+pub mod cli;
+pub mod decompile;
+pub mod disasm;
+mod error;
+mod instruction;
+pub mod io;
+mod limits;
+mod machine;
+mod memory;
+pub mod network;
+pub mod profile;
+pub mod symbolic;
+pub mod trace;
+mod word;
+
+pub use error::IntcodeError;
+pub use instruction::{DecodeError, Instruction, Mode, Opcode};
+pub use io::IoDevice;
+pub use limits::{Limit, Limits, DEADLINE_INTERVAL};
+pub use machine::{Machine, Output};
+pub use memory::{Memory, PagedMemory, VecMemory};
+pub use word::Word;
+
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind};
+
+/// Reads a comma-separated Intcode program, possibly spread over several lines.
+///
+/// A word that is not a number is an `InvalidData` error naming its line.
+pub fn read_program<R: BufRead>(reader: R) -> Result<Vec<i64>, Error> {
+    let mut program = vec![];
+    for (number, line) in reader.lines().enumerate() {
+        for word in line?.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let word = word.parse().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: expected a number, found `{}`", number + 1, word),
+                )
+            })?;
+            program.push(word);
+        }
+    }
+    Ok(program)
+}
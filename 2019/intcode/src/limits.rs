@@ -0,0 +1,48 @@
+use std::fmt;
+use std::time::Instant;
+
+/// Bounds on what a machine may do before it stops with
+/// `IntcodeError::BudgetExhausted`.
+///
+/// A machine stopped by a limit is left as it was before the instruction
+/// that would have exceeded it, so raising the limit and running again
+/// carries on where it stopped.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Instructions the machine may have executed in total, counted by
+    /// `Machine::executed`.
+    pub instructions: Option<u64>,
+    /// Writes have to land below this address, which bounds the memory a
+    /// dense backend allocates.
+    pub memory: Option<usize>,
+    /// Time after which the machine stops, checked every
+    /// `DEADLINE_INTERVAL` instructions.
+    pub deadline: Option<Instant>,
+}
+
+/// How often, in instructions executed, the deadline is checked.
+pub const DEADLINE_INTERVAL: u64 = 1024;
+
+impl Limits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == Limits::default()
+    }
+}
+
+/// Which of the `Limits` a machine ran into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Instructions,
+    Memory,
+    Deadline,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Instructions => "instruction",
+            Limit::Memory => "memory",
+            Limit::Deadline => "time",
+        })
+    }
+}
@@ -0,0 +1,432 @@
+use crate::error::IntcodeError;
+use crate::instruction::{DecodeError, Instruction, Mode, Opcode};
+use crate::io::IoDevice;
+use crate::limits::{Limit, Limits, DEADLINE_INTERVAL};
+use crate::memory::{Memory, VecMemory};
+use crate::word::Word;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io;
+use std::time::Instant;
+
+#[derive(Debug, PartialEq)]
+pub enum Output<W = i64> {
+    /// The program executed opcode 99; carries the value at address 0.
+    Halt(W),
+    /// The program executed opcode 3 while the `input` queue was empty.
+    NeedsInput,
+    /// The program executed opcode 4.
+    Value(W),
+}
+
+/// What `Machine::execute` did: the output, if any, and the address written.
+pub(crate) type Executed<W> = (Option<Output<W>>, Option<usize>);
+
+/// A running Intcode program.
+///
+/// Cloning forks the machine; over `PagedMemory` the fork shares pages with
+/// the original until either side writes. `save` and `restore` checkpoint
+/// the complete state to a file.
+///
+/// The word type comes from the memory backend, `i64` by default. Addition
+/// and multiplication wrap around on overflow unless `checked` is set, in
+/// which case they fail with `IntcodeError::Overflow`. Setting `limits`
+/// bounds how long a run may take and how much memory it may use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "M: Serialize, M::Word: Serialize",
+    deserialize = "M: Deserialize<'de>, M::Word: Deserialize<'de>"
+))]
+pub struct Machine<M: Memory = VecMemory> {
+    pub memory: M,
+    /// Values waiting to be read by opcode 3, front first.
+    pub input: VecDeque<M::Word>,
+    pub ip: usize,
+    pub rb: i64,
+    #[serde(default)]
+    pub checked: bool,
+    /// Instructions executed so far; asking for input does not count.
+    #[serde(default)]
+    pub executed: u64,
+    /// Not saved by `save`, since a deadline only means something to the
+    /// running process.
+    #[serde(skip)]
+    pub limits: Limits,
+}
+
+impl Machine {
+    pub fn new(memory: &[i64]) -> Machine {
+        Machine::with_memory(VecMemory::load(memory))
+    }
+
+    pub fn with_input(memory: &[i64], input: i64) -> Machine {
+        let mut m = Machine::new(memory);
+        m.input.push_back(input);
+        m
+    }
+}
+
+impl<M: Memory> Machine<M> {
+    /// Creates a machine over an already loaded memory backend.
+    pub fn with_memory(memory: M) -> Machine<M> {
+        Machine {
+            memory,
+            input: VecDeque::new(),
+            ip: 0,
+            rb: 0,
+            checked: false,
+            executed: 0,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Writes ip, rb, pending input and memory as JSON.
+    pub fn save<W: io::Write>(&self, out: W) -> io::Result<()>
+    where
+        M: Serialize,
+        M::Word: Serialize,
+    {
+        serde_json::to_writer(out, self).map_err(io::Error::from)
+    }
+
+    /// Reads back a machine written by `save`.
+    pub fn restore<R: io::Read>(reader: R) -> io::Result<Machine<M>>
+    where
+        M: DeserializeOwned,
+        M::Word: DeserializeOwned,
+    {
+        serde_json::from_reader(reader).map_err(io::Error::from)
+    }
+
+    fn get_operand(&self, mode: Mode, i: usize) -> Result<M::Word, IntcodeError> {
+        match mode {
+            Mode::Immediate => Ok(self.get(i)),
+            _ => Ok(self.get(self.get_address(mode, i)?)),
+        }
+    }
+
+    fn get_address(&self, mode: Mode, i: usize) -> Result<usize, IntcodeError> {
+        let immediate = self.narrow(&self.get(i))?;
+        let address = match mode {
+            Mode::Position => immediate,
+            Mode::Relative => immediate
+                .checked_add(self.rb)
+                .ok_or_else(|| self.overflow())?,
+            Mode::Immediate => {
+                return Err(IntcodeError::ImmediateWrite {
+                    ip: self.ip,
+                    opcode: self.opcode(),
+                    rb: self.rb,
+                })
+            }
+        };
+        self.check_address(mode, address)
+    }
+
+    pub(crate) fn check_address(&self, mode: Mode, address: i64) -> Result<usize, IntcodeError> {
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                ip: self.ip,
+                opcode: self.opcode(),
+                mode: mode.digit(),
+                rb: self.rb,
+                address,
+            });
+        }
+        Ok(address as usize)
+    }
+
+    /// A word used as an address or offset, which has to fit in an `i64`.
+    pub(crate) fn narrow(&self, word: &M::Word) -> Result<i64, IntcodeError> {
+        word.to_i64().ok_or_else(|| self.overflow())
+    }
+
+    pub(crate) fn overflow(&self) -> IntcodeError {
+        IntcodeError::Overflow {
+            ip: self.ip,
+            opcode: self.opcode(),
+        }
+    }
+
+    /// The raw opcode at `ip`, saturated if it does not even fit in an `i64`
+    /// (it is invalid either way).
+    fn opcode(&self) -> i64 {
+        self.get(self.ip).to_i64().unwrap_or(i64::MAX)
+    }
+
+    pub fn get(&self, i: usize) -> M::Word {
+        self.memory.get(i)
+    }
+
+    pub fn set(&mut self, i: usize, v: M::Word) {
+        self.memory.set(i, v);
+    }
+
+    pub(crate) fn decode(&self) -> Result<Instruction, IntcodeError> {
+        let opcode = self.opcode();
+        Instruction::decode(opcode).map_err(|e| match e {
+            DecodeError::InvalidOpcode => IntcodeError::InvalidOpcode {
+                ip: self.ip,
+                opcode,
+            },
+            DecodeError::InvalidMode(mode) => IntcodeError::InvalidMode {
+                ip: self.ip,
+                opcode,
+                mode,
+                rb: self.rb,
+            },
+        })
+    }
+
+    /// Runs until the program produces output, needs input or halts.
+    pub fn run(&mut self) -> Result<Output<M::Word>, IntcodeError> {
+        loop {
+            if let Some(output) = self.step()? {
+                return Ok(output);
+            }
+        }
+    }
+
+    /// Runs until `n` values have been output and returns them.
+    ///
+    /// Fewer values come back if the program halts or needs input first;
+    /// `is_halted` tells the two apart.
+    pub fn run_until_output_count(&mut self, n: usize) -> Result<Vec<M::Word>, IntcodeError> {
+        let mut outputs = vec![];
+        while outputs.len() < n {
+            match self.run()? {
+                Output::Value(v) => outputs.push(v),
+                Output::Halt(_) | Output::NeedsInput => break,
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Runs to completion on the queued input and returns everything output.
+    pub fn run_to_halt(&mut self) -> Result<Vec<M::Word>, IntcodeError> {
+        let mut outputs = vec![];
+        loop {
+            match self.run()? {
+                Output::Value(v) => outputs.push(v),
+                Output::Halt(_) => return Ok(outputs),
+                Output::NeedsInput => {
+                    return Err(IntcodeError::MissingInput {
+                        ip: self.ip,
+                        opcode: self.opcode(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Whether the next instruction is opcode 99.
+    pub fn is_halted(&self) -> bool {
+        self.decode().map(|i| i.opcode) == Ok(Opcode::Hlt)
+    }
+
+    /// Executes a single instruction.
+    ///
+    /// Returns `None` when the instruction completed without producing an
+    /// `Output`. `NeedsInput` and `Halt` leave `ip` on the same instruction.
+    pub fn step(&mut self) -> Result<Option<Output<M::Word>>, IntcodeError> {
+        let instruction = self.decode()?;
+        self.execute(instruction).map(|(output, _)| output)
+    }
+
+    /// Executes `instruction` as the one at `ip`, whatever memory holds there.
+    ///
+    /// Also returns the address written to, if any, for engines that cache
+    /// decoded instructions.
+    pub(crate) fn execute(
+        &mut self,
+        instruction: Instruction,
+    ) -> Result<Executed<M::Word>, IntcodeError> {
+        self.check_budget()?;
+        let executed = self.apply(instruction)?;
+        if !matches!(executed.0, Some(Output::NeedsInput)) {
+            self.executed += 1;
+        }
+        Ok(executed)
+    }
+
+    pub(crate) fn check_budget(&self) -> Result<(), IntcodeError> {
+        if let Some(max) = self.limits.instructions {
+            if self.executed >= max {
+                return Err(self.exhausted(Limit::Instructions));
+            }
+        }
+        if let Some(deadline) = self.limits.deadline {
+            if self.executed.is_multiple_of(DEADLINE_INTERVAL) && Instant::now() >= deadline {
+                return Err(self.exhausted(Limit::Deadline));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_write(&self, to: usize) -> Result<(), IntcodeError> {
+        match self.limits.memory {
+            Some(max) if to >= max => Err(self.exhausted(Limit::Memory)),
+            _ => Ok(()),
+        }
+    }
+
+    fn exhausted(&self, limit: Limit) -> IntcodeError {
+        IntcodeError::BudgetExhausted {
+            ip: self.ip,
+            opcode: self.opcode(),
+            limit,
+        }
+    }
+
+    fn apply(&mut self, instruction: Instruction) -> Result<Executed<M::Word>, IntcodeError> {
+        let Instruction { opcode: op, modes } = instruction;
+        let mut written = None;
+        match op {
+            Opcode::Add | Opcode::Mul => {
+                let op1 = self.get_operand(modes[0], self.ip + 1)?;
+                let op2 = self.get_operand(modes[1], self.ip + 2)?;
+                let to = self.get_address(modes[2], self.ip + 3)?;
+                let result = match (op, self.checked) {
+                    (Opcode::Add, false) => op1.wrapping_add(&op2),
+                    (_, false) => op1.wrapping_mul(&op2),
+                    (Opcode::Add, true) => op1.checked_add(&op2).ok_or_else(|| self.overflow())?,
+                    (_, true) => op1.checked_mul(&op2).ok_or_else(|| self.overflow())?,
+                };
+                self.check_write(to)?;
+                self.set(to, result);
+                written = Some(to);
+                self.ip += 4;
+            }
+            Opcode::In => {
+                if self.input.is_empty() {
+                    return Ok((Some(Output::NeedsInput), None));
+                }
+                let to = self.get_address(modes[0], self.ip + 1)?;
+                self.check_write(to)?;
+                let input = self.input.pop_front().unwrap();
+                self.set(to, input);
+                written = Some(to);
+                self.ip += 2;
+            }
+            Opcode::Out => {
+                let value = self.get_operand(modes[0], self.ip + 1)?;
+                self.ip += 2;
+                return Ok((Some(Output::Value(value)), None));
+            }
+            Opcode::Jnz | Opcode::Jz => {
+                let cond = self.get_operand(modes[0], self.ip + 1)?;
+                let destination = self.get_operand(modes[1], self.ip + 2)?;
+                if (op == Opcode::Jnz) != cond.is_zero() {
+                    let destination = self.narrow(&destination)?;
+                    self.ip = self.check_address(modes[1], destination)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            Opcode::Lt | Opcode::Eq => {
+                let op1 = self.get_operand(modes[0], self.ip + 1)?;
+                let op2 = self.get_operand(modes[1], self.ip + 2)?;
+                let to = self.get_address(modes[2], self.ip + 3)?;
+                self.check_write(to)?;
+                if (op == Opcode::Lt && op1 < op2) || (op == Opcode::Eq && op1 == op2) {
+                    self.set(to, M::Word::from_i64(1));
+                } else {
+                    self.set(to, M::Word::from_i64(0));
+                }
+                written = Some(to);
+                self.ip += 4;
+            }
+            Opcode::Arb => {
+                let offset = self.get_operand(modes[0], self.ip + 1)?;
+                let offset = self.narrow(&offset)?;
+                self.rb = match self.checked {
+                    false => self.rb.wrapping_add(offset),
+                    true => self.rb.checked_add(offset).ok_or_else(|| self.overflow())?,
+                };
+                self.ip += 2;
+            }
+            Opcode::Hlt => return Ok((Some(Output::Halt(self.get(0))), None)),
+        }
+        Ok((None, written))
+    }
+}
+
+/// Input and output devices deal in `i64` words.
+impl<M: Memory<Word = i64>> Machine<M> {
+    /// Runs with `device` supplying input and receiving output.
+    ///
+    /// Queued input is used before the device is asked. Returns `Halt`, or
+    /// `NeedsInput` once the device has nothing more to give. A device error
+    /// is reported as `IntcodeError::Device` at the instruction it served.
+    pub fn run_with<D: IoDevice>(&mut self, device: &mut D) -> Result<Output, IntcodeError> {
+        loop {
+            match self.run()? {
+                // ip has moved past the two-word output instruction.
+                Output::Value(v) => device
+                    .write(v)
+                    .map_err(|e| self.device_error(self.ip - 2, e))?,
+                Output::NeedsInput => {
+                    match device.read().map_err(|e| self.device_error(self.ip, e))? {
+                        Some(v) => self.input.push_back(v),
+                        None => return Ok(Output::NeedsInput),
+                    }
+                }
+                halt => return Ok(halt),
+            }
+        }
+    }
+
+    pub(crate) fn device_error(&self, ip: usize, error: io::Error) -> IntcodeError {
+        IntcodeError::Device {
+            ip,
+            opcode: self.get(ip),
+            message: error.to_string(),
+        }
+    }
+
+    /// Runs to completion as a future, returning the value at address 0.
+    ///
+    /// Whenever the queue is empty, opcode 3 awaits the next value of
+    /// `input`; opcode 4 sends into `output`. The end of `input` is a
+    /// `MissingInput` error and a send the sink refuses, because its
+    /// receiver is gone, an `OutputClosed` error for the opcode 4. Since
+    /// the machine yields only while waiting, any number of them can share
+    /// a single-threaded executor, wired together through channels.
+    pub async fn run_async<I, O>(
+        &mut self,
+        mut input: I,
+        mut output: O,
+    ) -> Result<i64, IntcodeError>
+    where
+        I: Stream<Item = i64> + Unpin,
+        O: Sink<i64> + Unpin,
+    {
+        loop {
+            match self.run()? {
+                Output::Value(v) => {
+                    if output.send(v).await.is_err() {
+                        // The value is lost, but the machine is left after the
+                        // instruction that produced it.
+                        let ip = self.ip - 2;
+                        return Err(IntcodeError::OutputClosed {
+                            ip,
+                            opcode: self.get(ip),
+                        });
+                    }
+                }
+                Output::NeedsInput => match input.next().await {
+                    Some(v) => self.input.push_back(v),
+                    None => {
+                        return Err(IntcodeError::MissingInput {
+                            ip: self.ip,
+                            opcode: self.opcode(),
+                        })
+                    }
+                },
+                Output::Halt(v) => return Ok(v),
+            }
+        }
+    }
+}
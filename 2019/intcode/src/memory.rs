@@ -0,0 +1,127 @@
+use crate::word::Word;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Storage for a machine's memory. Cells never written read as 0.
+pub trait Memory {
+    type Word: Word;
+    /// Builds memory holding `program` at address 0 onwards.
+    fn load(program: &[Self::Word]) -> Self;
+    fn get(&self, address: usize) -> Self::Word;
+    fn set(&mut self, address: usize, value: Self::Word);
+}
+
+/// Dense memory that grows to cover the highest address written.
+///
+/// Fastest for ordinary programs, but a write to a huge address allocates
+/// everything below it; use `PagedMemory` for those.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VecMemory<W = i64>(Vec<W>);
+
+impl<W: Word> Memory for VecMemory<W> {
+    type Word = W;
+
+    fn load(program: &[W]) -> VecMemory<W> {
+        VecMemory(program.to_vec())
+    }
+
+    fn get(&self, address: usize) -> W {
+        match self.0.get(address) {
+            Some(v) => v.clone(),
+            None => W::from_i64(0),
+        }
+    }
+
+    fn set(&mut self, address: usize, value: W) {
+        if address >= self.0.len() {
+            self.0.resize(address + 1, W::from_i64(0));
+        }
+        self.0[address] = value;
+    }
+}
+
+const PAGE_BITS: usize = 10;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+/// Pages below this index live in a directly indexed table, the rest in a map.
+const NEAR_PAGES: usize = 1 << 16;
+
+/// Sparse memory allocated in fixed-size pages on first write.
+///
+/// Pages are shared between clones and copied on first write, so forking a
+/// machine costs one page table and each fork pays only for what it changes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PagedMemory<W = i64> {
+    near: Vec<Option<Arc<[W]>>>,
+    far: HashMap<usize, Arc<[W]>>,
+}
+
+impl<W: Word> PagedMemory<W> {
+    fn page(&self, page: usize) -> Option<&[W]> {
+        if page < NEAR_PAGES {
+            self.near.get(page).and_then(|p| p.as_deref())
+        } else {
+            self.far.get(&page).map(|p| &p[..])
+        }
+    }
+
+    fn page_mut(&mut self, page: usize) -> &mut [W] {
+        let blank = || Arc::from(vec![W::from_i64(0); PAGE_SIZE]);
+        let shared = if page < NEAR_PAGES {
+            if page >= self.near.len() {
+                self.near.resize(page + 1, None);
+            }
+            self.near[page].get_or_insert_with(blank)
+        } else {
+            self.far.entry(page).or_insert_with(blank)
+        };
+        Arc::make_mut(shared)
+    }
+}
+
+impl<W: Word> Memory for PagedMemory<W> {
+    type Word = W;
+
+    fn load(program: &[W]) -> PagedMemory<W> {
+        let mut memory = PagedMemory {
+            near: vec![],
+            far: HashMap::new(),
+        };
+        for (page, chunk) in program.chunks(PAGE_SIZE).enumerate() {
+            memory.page_mut(page)[..chunk.len()].clone_from_slice(chunk);
+        }
+        memory
+    }
+
+    fn get(&self, address: usize) -> W {
+        match self.page(address >> PAGE_BITS) {
+            Some(page) => page[address & (PAGE_SIZE - 1)].clone(),
+            None => W::from_i64(0),
+        }
+    }
+
+    fn set(&mut self, address: usize, value: W) {
+        self.page_mut(address >> PAGE_BITS)[address & (PAGE_SIZE - 1)] = value;
+    }
+}
+
+/// The original map-per-cell storage, kept for comparison.
+impl<W: Word> Memory for BTreeMap<usize, W> {
+    type Word = W;
+
+    fn load(program: &[W]) -> BTreeMap<usize, W> {
+        program.iter().cloned().enumerate().collect()
+    }
+
+    fn get(&self, address: usize) -> W {
+        match BTreeMap::get(self, &address) {
+            Some(v) => v.clone(),
+            None => W::from_i64(0),
+        }
+    }
+
+    fn set(&mut self, address: usize, value: W) {
+        self.insert(address, value);
+    }
+}
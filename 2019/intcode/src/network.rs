@@ -0,0 +1,357 @@
+//! Networks of machines running on their own threads, wired output to input.
+//!
+//! `chain` and `ring` pass values from each machine to the next, `bus`
+//! routes `(address, x, y)` packets between machines with a NAT watching
+//! for the network to go idle. Each run ends when every machine halts or
+//! when the network is quiescent, meaning nothing can happen any more
+//! because every live machine waits for input that will never come.
+
+use crate::error::IntcodeError;
+use crate::io::IoDevice;
+use crate::machine::Machine;
+use crate::memory::Memory;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+
+/// The address packets for the NAT are sent to on a `bus`.
+pub const NAT: i64 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// Every machine halted.
+    Halted,
+    /// The machines still running can make no further progress.
+    Quiescent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub outcome: Outcome,
+    /// Every value output by the last machine.
+    pub output: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusReport {
+    pub outcome: Outcome,
+    /// Packets sent to the NAT, as `(x, y)`.
+    pub received: Vec<(i64, i64)>,
+    /// Packets the NAT sent to address 0 to wake the network up.
+    pub forwarded: Vec<(i64, i64)>,
+}
+
+/// Runs the machines as a pipeline: `input` goes to the first machine, each
+/// machine's output to the next, and the last machine's output is collected.
+pub fn chain<M: Memory<Word = i64> + Send>(
+    machines: Vec<Machine<M>>,
+    input: &[i64],
+) -> Result<Report, IntcodeError> {
+    pipeline(machines, input, false)
+}
+
+/// Like `chain`, but the last machine's output is also fed back to the first.
+pub fn ring<M: Memory<Word = i64> + Send>(
+    machines: Vec<Machine<M>>,
+    input: &[i64],
+) -> Result<Report, IntcodeError> {
+    pipeline(machines, input, true)
+}
+
+fn pipeline<M: Memory<Word = i64> + Send>(
+    machines: Vec<Machine<M>>,
+    input: &[i64],
+    ring: bool,
+) -> Result<Report, IntcodeError> {
+    let network = Network::new(machines.len());
+    if let Some(queue) = network.lock().queues.first_mut() {
+        queue.extend(input);
+    }
+    let last = machines.len().saturating_sub(1);
+    network.run(machines, |id| Node {
+        id,
+        network: &network,
+        route: match id {
+            _ if id < last => Route::Next,
+            _ if ring => Route::Back,
+            _ => Route::Out,
+        },
+        packet: vec![],
+    })?;
+    let state = network.state.into_inner().unwrap();
+    Ok(Report {
+        outcome: state.stop.unwrap(),
+        output: state.output,
+    })
+}
+
+/// Runs the machines as hosts on a packet network.
+///
+/// Machine `i` is first given its address `i`, and reads `-1` whenever no
+/// packet is waiting for it. Every three values a machine outputs form a
+/// packet `(address, x, y)`; packets for `NAT` are kept by the NAT, those
+/// for unknown addresses are dropped. Once every machine has polled an
+/// empty queue twice without sending anything, the network is idle and the
+/// NAT sends the last packet it received to address 0. The network is
+/// quiescent when it goes idle and the NAT has nothing new to send: no
+/// packet at all, or the same one it sent last time, which is then
+/// recorded a second time.
+pub fn bus<M: Memory<Word = i64> + Send>(
+    mut machines: Vec<Machine<M>>,
+) -> Result<BusReport, IntcodeError> {
+    for (address, machine) in machines.iter_mut().enumerate() {
+        machine.input.push_back(address as i64);
+    }
+    let network = Network::new(machines.len());
+    network.run(machines, |id| Node {
+        id,
+        network: &network,
+        route: Route::Bus,
+        packet: vec![],
+    })?;
+    let state = network.state.into_inner().unwrap();
+    Ok(BusReport {
+        outcome: state.stop.unwrap(),
+        received: state.received,
+        forwarded: state.forwarded,
+    })
+}
+
+struct State {
+    queues: Vec<VecDeque<i64>>,
+    /// Machines blocked waiting for a value (pipelines only).
+    waiting: usize,
+    live: usize,
+    /// Machines that stopped running.
+    done: Vec<bool>,
+    /// Set once the run is over; machines still asking for input get none.
+    stop: Option<Outcome>,
+    output: Vec<i64>,
+    /// Consecutive empty polls per bus host.
+    starved: Vec<u32>,
+    nat: Option<(i64, i64)>,
+    received: Vec<(i64, i64)>,
+    forwarded: Vec<(i64, i64)>,
+}
+
+impl State {
+    /// Whether every running machine has nothing to read.
+    fn drained(&self) -> bool {
+        self.queues
+            .iter()
+            .zip(&self.done)
+            .all(|(queue, &done)| done || queue.is_empty())
+    }
+
+    /// Whether every running pipeline machine waits on an empty queue. A
+    /// machine just handed a value counts as waiting until it wakes up, so
+    /// the queues have to be checked too.
+    fn stuck(&self) -> bool {
+        self.waiting == self.live && self.drained()
+    }
+
+    /// Whether every running bus host has polled twice with nothing to read.
+    fn idle(&self) -> bool {
+        self.drained()
+            && self
+                .starved
+                .iter()
+                .zip(&self.done)
+                .all(|(&n, &done)| done || n >= 2)
+    }
+}
+
+struct Network {
+    state: Mutex<State>,
+    wake: Condvar,
+}
+
+impl Network {
+    fn new(size: usize) -> Network {
+        Network {
+            state: Mutex::new(State {
+                queues: vec![VecDeque::new(); size],
+                waiting: 0,
+                live: size,
+                done: vec![false; size],
+                stop: None,
+                output: vec![],
+                starved: vec![0; size],
+                nat: None,
+                received: vec![],
+                forwarded: vec![],
+            }),
+            wake: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Runs every machine on its own thread against the device made for it.
+    fn run<'a, M, F>(&'a self, machines: Vec<Machine<M>>, device: F) -> Result<(), IntcodeError>
+    where
+        M: Memory<Word = i64> + Send,
+        F: Fn(usize) -> Node<'a>,
+    {
+        if machines.is_empty() {
+            self.lock().stop = Some(Outcome::Halted);
+        }
+        let results = thread::scope(|scope| {
+            let handles = machines
+                .into_iter()
+                .enumerate()
+                .map(|(id, mut machine)| {
+                    let mut node = device(id);
+                    scope.spawn(move || {
+                        let result = machine.run_with(&mut node);
+                        self.finish(id, result.is_err());
+                        result
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        for result in results {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Accounts for a machine that stopped running.
+    fn finish(&self, id: usize, failed: bool) {
+        let mut state = self.lock();
+        state.live -= 1;
+        state.done[id] = true;
+        if state.stop.is_none() {
+            if state.live == 0 {
+                state.stop = Some(Outcome::Halted);
+            } else if failed || state.stuck() {
+                // A failed run is reported through its error; stopping the
+                // rest keeps them from waiting on it forever.
+                state.stop = Some(Outcome::Quiescent);
+            }
+        }
+        self.wake.notify_all();
+    }
+}
+
+enum Route {
+    /// Output goes to the next machine.
+    Next,
+    /// Output is collected.
+    Out,
+    /// Output is collected and fed back to the first machine.
+    Back,
+    /// Output is grouped into packets.
+    Bus,
+}
+
+struct Node<'a> {
+    id: usize,
+    network: &'a Network,
+    route: Route,
+    packet: Vec<i64>,
+}
+
+impl Node<'_> {
+    fn poll(&self) -> Option<i64> {
+        let mut state = self.network.lock();
+        if state.stop.is_some() {
+            return None;
+        }
+        if let Some(v) = state.queues[self.id].pop_front() {
+            state.starved[self.id] = 0;
+            return Some(v);
+        }
+        state.starved[self.id] += 1;
+        if state.idle() {
+            match state.nat {
+                Some(packet) => {
+                    let repeated = state.forwarded.last() == Some(&packet);
+                    state.forwarded.push(packet);
+                    if repeated {
+                        state.stop = Some(Outcome::Quiescent);
+                        return None;
+                    }
+                    state.queues[0].extend(&[packet.0, packet.1]);
+                    for n in state.starved.iter_mut() {
+                        *n = 0;
+                    }
+                }
+                None => {
+                    state.stop = Some(Outcome::Quiescent);
+                    return None;
+                }
+            }
+        }
+        Some(-1)
+    }
+
+    fn send(&mut self, value: i64) {
+        self.packet.push(value);
+        if let [address, x, y] = self.packet[..] {
+            self.packet.clear();
+            let mut state = self.network.lock();
+            state.starved[self.id] = 0;
+            if address == NAT {
+                state.nat = Some((x, y));
+                state.received.push((x, y));
+            } else if let Some(queue) = state.queues.get_mut(address as usize) {
+                queue.extend(&[x, y]);
+            }
+        }
+    }
+}
+
+impl IoDevice for Node<'_> {
+    fn read(&mut self) -> io::Result<Option<i64>> {
+        if let Route::Bus = self.route {
+            return Ok(self.poll());
+        }
+        let mut state = self.network.lock();
+        loop {
+            if state.stop.is_some() {
+                return Ok(None);
+            }
+            if let Some(v) = state.queues[self.id].pop_front() {
+                return Ok(Some(v));
+            }
+            state.waiting += 1;
+            if state.stuck() {
+                state.waiting -= 1;
+                state.stop = Some(Outcome::Quiescent);
+                self.network.wake.notify_all();
+                return Ok(None);
+            }
+            state = self.network.wake.wait(state).unwrap();
+            state.waiting -= 1;
+        }
+    }
+
+    fn write(&mut self, value: i64) -> io::Result<()> {
+        let next = match self.route {
+            Route::Bus => {
+                self.send(value);
+                return Ok(());
+            }
+            Route::Next => Some(self.id + 1),
+            Route::Back => Some(0),
+            Route::Out => None,
+        };
+        let mut state = self.network.lock();
+        if let Route::Back | Route::Out = self.route {
+            state.output.push(value);
+        }
+        if let Some(next) = next {
+            state.queues[next].push_back(value);
+            self.network.wake.notify_all();
+        }
+        Ok(())
+    }
+}
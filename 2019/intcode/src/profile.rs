@@ -0,0 +1,165 @@
+//! Execution counts per instruction and opcode, for finding hot spots.
+
+use crate::disasm;
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::machine::{Machine, Output};
+use crate::memory::Memory;
+use crate::word::Word;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// Executions per instruction address.
+    pub by_address: Vec<u64>,
+    /// Executions per opcode, indexed like `Opcode::ALL`.
+    pub by_opcode: [u64; 10],
+    pub instructions: u64,
+    pub inputs: u64,
+    pub outputs: u64,
+    /// Every address read or written through a parameter.
+    pub touched: HashSet<usize>,
+}
+
+impl Profile {
+    pub fn new() -> Profile {
+        Profile::default()
+    }
+
+    /// Executes one instruction with `Machine::step`, counting it.
+    ///
+    /// An instruction that could not execute (`NeedsInput` or an error) is
+    /// not counted.
+    pub fn step<M: Memory>(
+        &mut self,
+        machine: &mut Machine<M>,
+    ) -> Result<Option<Output<M::Word>>, IntcodeError> {
+        let ip = machine.ip;
+        let instruction = machine
+            .get(ip)
+            .to_i64()
+            .and_then(|raw| Instruction::decode(raw).ok());
+        let rb = machine.rb;
+        let output = machine.step()?;
+        let instruction = match instruction {
+            Some(instruction) if output != Some(Output::NeedsInput) => instruction,
+            _ => return Ok(output),
+        };
+        if ip >= self.by_address.len() {
+            self.by_address.resize(ip + 1, 0);
+        }
+        self.by_address[ip] += 1;
+        self.by_opcode[instruction.opcode as usize] += 1;
+        self.instructions += 1;
+        match instruction.opcode {
+            Opcode::In => self.inputs += 1,
+            Opcode::Out => self.outputs += 1,
+            _ => {}
+        }
+        for (i, &mode) in instruction
+            .modes
+            .iter()
+            .enumerate()
+            .take(instruction.opcode.params())
+        {
+            let param = match machine.get(ip + 1 + i).to_i64() {
+                Some(param) => param,
+                None => continue,
+            };
+            let address = match mode {
+                Mode::Position => param,
+                Mode::Relative => param.wrapping_add(rb),
+                Mode::Immediate => continue,
+            };
+            if address >= 0 {
+                self.touched.insert(address as usize);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Like `Machine::run`, counting each instruction along the way.
+    pub fn run<M: Memory>(
+        &mut self,
+        machine: &mut Machine<M>,
+    ) -> Result<Output<M::Word>, IntcodeError> {
+        loop {
+            if let Some(output) = self.step(machine)? {
+                return Ok(output);
+            }
+        }
+    }
+
+    /// Summarises the counts, listing the `top` most executed instructions
+    /// disassembled from the memory `read` gives access to.
+    pub fn report<F: Fn(usize) -> i64>(&self, read: F, top: usize) -> String {
+        let share = |count: u64| 100.0 * count as f64 / self.instructions.max(1) as f64;
+        let mut out = String::new();
+        writeln!(out, "instructions  {}", self.instructions).unwrap();
+        writeln!(out, "inputs        {}", self.inputs).unwrap();
+        writeln!(out, "outputs       {}", self.outputs).unwrap();
+        writeln!(
+            out,
+            "code          {} distinct instructions executed",
+            self.by_address.iter().filter(|&&n| n > 0).count()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "memory        {} cells touched, highest address {}",
+            self.touched.len(),
+            self.touched.iter().max().map_or(0, |&a| a)
+        )
+        .unwrap();
+
+        writeln!(out).unwrap();
+        writeln!(out, "{:<6}  {:>10}  {:>6}", "opcode", "count", "share").unwrap();
+        let mut opcodes = Opcode::ALL
+            .iter()
+            .zip(self.by_opcode.iter())
+            .filter(|(_, &count)| count > 0)
+            .collect::<Vec<_>>();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1));
+        for (opcode, &count) in opcodes {
+            writeln!(
+                out,
+                "{:<6}  {:>10}  {:>5.1}%",
+                opcode.mnemonic(),
+                count,
+                share(count)
+            )
+            .unwrap();
+        }
+
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "{:>7}  {:>10}  {:>6}  {:>5}  instruction",
+            "address", "count", "share", "cumul"
+        )
+        .unwrap();
+        let mut hot = self
+            .by_address
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .collect::<Vec<_>>();
+        hot.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(&b.0)));
+        let mut cumulative = 0;
+        for (address, &count) in hot.into_iter().take(top) {
+            cumulative += count;
+            writeln!(
+                out,
+                "{:>7}  {:>10}  {:>5.1}%  {:>4.0}%  {}",
+                address,
+                count,
+                share(count),
+                share(cumulative),
+                disasm::describe(&read, address).0
+            )
+            .unwrap();
+        }
+        out
+    }
+}
@@ -0,0 +1,580 @@
+//! Searching for the memory values that make a program produce a result.
+//!
+//! Day 2 asks which noun and verb, stored at addresses 1 and 2, make the
+//! program halt with 19690720 at address 0. `SymbolicMachine` runs the
+//! program with those cells left as symbols, so address 0 ends up holding a
+//! polynomial in them that `solve` can solve directly. That only works while
+//! the symbols do not decide the control flow, which addresses are written
+//! or what code runs; `search` falls back to `brute_force`, which tries
+//! every combination in parallel, when they do.
+
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::machine::{Machine, Output};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::ops::{Add, Mul, RangeInclusive};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::thread;
+
+/// Instructions a symbolic run or a single brute-force run may execute.
+pub const STEP_LIMIT: u64 = 1 << 20;
+
+/// A polynomial over the initial values of symbolic memory cells, with the
+/// machine's wrapping `i64` arithmetic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Poly {
+    /// Coefficients by monomial, each monomial being the addresses of the
+    /// cells multiplied, in order. None of the coefficients is zero.
+    terms: BTreeMap<Vec<usize>, i64>,
+}
+
+impl Poly {
+    pub fn constant(value: i64) -> Poly {
+        let mut terms = BTreeMap::new();
+        if value != 0 {
+            terms.insert(vec![], value);
+        }
+        Poly { terms }
+    }
+
+    /// The initial value of the cell at `address`.
+    pub fn cell(address: usize) -> Poly {
+        let mut terms = BTreeMap::new();
+        terms.insert(vec![address], 1);
+        Poly { terms }
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        match self.terms.len() {
+            0 => Some(0),
+            1 => self.terms.get(&vec![]).copied(),
+            _ => None,
+        }
+    }
+
+    /// The addresses of the cells the polynomial depends on.
+    pub fn cells(&self) -> BTreeSet<usize> {
+        self.terms.keys().flatten().copied().collect()
+    }
+
+    /// Evaluates the polynomial with `cell` giving each cell's value.
+    pub fn eval<F: Fn(usize) -> i64>(&self, cell: F) -> i64 {
+        self.terms
+            .iter()
+            .fold(0i64, |sum, (monomial, &coefficient)| {
+                let term = monomial.iter().fold(coefficient, |product, &address| {
+                    product.wrapping_mul(cell(address))
+                });
+                sum.wrapping_add(term)
+            })
+    }
+
+    fn accumulate(&mut self, monomial: Vec<usize>, coefficient: i64) {
+        let entry = self.terms.entry(monomial).or_insert(0);
+        *entry = entry.wrapping_add(coefficient);
+        if *entry == 0 {
+            self.terms.retain(|_, &mut c| c != 0);
+        }
+    }
+
+    /// The coefficient of `address` if the polynomial is linear in it, as
+    /// `c * [address] + rest` with `rest` not depending on it.
+    fn linear(&self, address: usize) -> Option<(i64, Poly)> {
+        let mut rest = self.clone();
+        let coefficient = rest.terms.remove(&vec![address])?;
+        if rest.cells().contains(&address) {
+            return None;
+        }
+        Some((coefficient, rest))
+    }
+}
+
+impl Add for Poly {
+    type Output = Poly;
+
+    fn add(mut self, other: Poly) -> Poly {
+        for (monomial, coefficient) in other.terms {
+            self.accumulate(monomial, coefficient);
+        }
+        self
+    }
+}
+
+impl Mul for Poly {
+    type Output = Poly;
+
+    fn mul(self, other: Poly) -> Poly {
+        let mut product = Poly::default();
+        for (a, &x) in &self.terms {
+            for (b, &y) in &other.terms {
+                let mut monomial = a.iter().chain(b).copied().collect::<Vec<_>>();
+                monomial.sort_unstable();
+                product.accumulate(monomial, x.wrapping_mul(y));
+            }
+        }
+        product
+    }
+}
+
+impl fmt::Display for Poly {
+    /// Writes terms such as `3 * [1] * [2]`, constant last.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return f.write_str("0");
+        }
+        let mut terms = self.terms.iter().collect::<Vec<_>>();
+        terms.rotate_left(usize::from(self.terms.contains_key(&vec![])));
+        for (i, (monomial, &coefficient)) in terms.into_iter().enumerate() {
+            let mut factors = monomial
+                .iter()
+                .map(|address| format!("[{}]", address))
+                .collect::<Vec<_>>();
+            let magnitude = coefficient.unsigned_abs();
+            if magnitude != 1 || factors.is_empty() {
+                factors.insert(0, magnitude.to_string());
+            }
+            match (i, coefficient < 0) {
+                (0, true) => f.write_str("-")?,
+                (0, false) => {}
+                (_, true) => f.write_str(" - ")?,
+                (_, false) => f.write_str(" + ")?,
+            }
+            f.write_str(&factors.join(" * "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Why a program could not be run symbolically.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolicError {
+    /// The opcode at `ip` depends on the symbols.
+    SymbolicCode { ip: usize },
+    /// A write, jump target or relative base change at `ip` depends on
+    /// the symbols.
+    SymbolicAddress { ip: usize },
+    /// Whether the jump at `ip` is taken depends on the symbols.
+    SymbolicBranch { ip: usize },
+    /// The instruction at `ip` outputs a value read through an address that
+    /// depends on the symbols, or a comparison of symbols.
+    UnknownValue { ip: usize },
+    /// The instruction at `ip` would fault whatever the symbols are.
+    Fault(IntcodeError),
+    /// The program halted, but no values within the cells' ranges give the
+    /// target, or the ones `solve` found do not when run for real.
+    NoSymbolicSolution,
+    /// The program ran for `STEP_LIMIT` instructions.
+    StepLimit,
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::SymbolicCode { ip } => write!(f, "symbolic opcode at ip {}", ip),
+            SymbolicError::SymbolicAddress { ip } => {
+                write!(f, "symbolic address at ip {}", ip)
+            }
+            SymbolicError::SymbolicBranch { ip } => write!(f, "symbolic branch at ip {}", ip),
+            SymbolicError::UnknownValue { ip } => write!(f, "unknown value at ip {}", ip),
+            SymbolicError::Fault(e) => e.fmt(f),
+            SymbolicError::NoSymbolicSolution => write!(f, "no symbolic solution"),
+            SymbolicError::StepLimit => write!(f, "no halt within {} instructions", STEP_LIMIT),
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+/// A machine whose memory holds polynomials over some of its initial cells.
+///
+/// A cell is unknown (`None`) once it has been loaded through an address
+/// that depends on the symbols. That is harmless as long as nothing that
+/// matters reads it before it is overwritten.
+#[derive(Debug, Clone)]
+pub struct SymbolicMachine {
+    memory: Vec<Option<Poly>>,
+    /// Values waiting to be read by opcode 3, front first.
+    pub input: VecDeque<i64>,
+    pub ip: usize,
+    pub rb: i64,
+    pub executed: u64,
+}
+
+impl SymbolicMachine {
+    /// Loads `program` with the cells at `symbols` left symbolic.
+    pub fn new(program: &[i64], symbols: &[usize]) -> SymbolicMachine {
+        let mut memory = program
+            .iter()
+            .map(|&word| Some(Poly::constant(word)))
+            .collect::<Vec<_>>();
+        for &address in symbols {
+            if address >= memory.len() {
+                memory.resize(address + 1, Some(Poly::default()));
+            }
+            memory[address] = Some(Poly::cell(address));
+        }
+        SymbolicMachine {
+            memory,
+            input: VecDeque::new(),
+            ip: 0,
+            rb: 0,
+            executed: 0,
+        }
+    }
+
+    /// The cell at `address`, or `None` if it is unknown.
+    pub fn get(&self, address: usize) -> Option<&Poly> {
+        match self.memory.get(address) {
+            Some(cell) => cell.as_ref(),
+            None => Some(&ZERO),
+        }
+    }
+
+    fn set(&mut self, address: usize, value: Option<Poly>) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, Some(Poly::default()));
+        }
+        self.memory[address] = value;
+    }
+
+    fn constant(&self, address: usize) -> Option<i64> {
+        self.get(address).and_then(Poly::as_constant)
+    }
+
+    /// The address parameter `i` refers to, or `None` if it depends on the
+    /// symbols.
+    fn address(&self, instruction: Instruction, i: usize) -> Result<Option<usize>, SymbolicError> {
+        let param = match self.constant(self.ip + 1 + i) {
+            Some(param) => param,
+            None => return Ok(None),
+        };
+        let address = match instruction.modes[i] {
+            Mode::Position => param,
+            Mode::Relative => param.wrapping_add(self.rb),
+            Mode::Immediate => {
+                return Err(self.fault(|ip, opcode| IntcodeError::ImmediateWrite {
+                    ip,
+                    opcode,
+                    rb: self.rb,
+                }))
+            }
+        };
+        if address < 0 {
+            return Err(self.fault(|ip, opcode| IntcodeError::NegativeAddress {
+                ip,
+                opcode,
+                mode: instruction.modes[i].digit(),
+                rb: self.rb,
+                address,
+            }));
+        }
+        Ok(Some(address as usize))
+    }
+
+    fn read(&self, instruction: Instruction, i: usize) -> Result<Option<Poly>, SymbolicError> {
+        if instruction.modes[i] == Mode::Immediate {
+            return Ok(self.get(self.ip + 1 + i).cloned());
+        }
+        Ok(self
+            .address(instruction, i)?
+            .and_then(|address| self.get(address).cloned()))
+    }
+
+    fn write(
+        &mut self,
+        instruction: Instruction,
+        i: usize,
+        value: Option<Poly>,
+    ) -> Result<(), SymbolicError> {
+        let ip = self.ip;
+        let address = self
+            .address(instruction, i)?
+            .ok_or(SymbolicError::SymbolicAddress { ip })?;
+        self.set(address, value);
+        Ok(())
+    }
+
+    fn fault<F: Fn(usize, i64) -> IntcodeError>(&self, error: F) -> SymbolicError {
+        SymbolicError::Fault(error(self.ip, self.constant(self.ip).unwrap_or(0)))
+    }
+
+    /// Runs until the program produces output, needs input or halts, like
+    /// `Machine::run`.
+    pub fn run(&mut self) -> Result<Output<Poly>, SymbolicError> {
+        loop {
+            if self.executed >= STEP_LIMIT {
+                return Err(SymbolicError::StepLimit);
+            }
+            let ip = self.ip;
+            let raw = self
+                .constant(ip)
+                .ok_or(SymbolicError::SymbolicCode { ip })?;
+            let instruction = Instruction::decode(raw).map_err(|_| {
+                SymbolicError::Fault(IntcodeError::InvalidOpcode { ip, opcode: raw })
+            })?;
+            let opcode = instruction.opcode;
+            if opcode == Opcode::In && self.input.is_empty() {
+                return Ok(Output::NeedsInput);
+            }
+            self.executed += 1;
+            self.ip += instruction.size();
+            let restore = self.ip;
+            self.ip = ip;
+            let known = |value: Option<Poly>| value.ok_or(SymbolicError::UnknownValue { ip });
+            match opcode {
+                Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+                    let a = self.read(instruction, 0)?;
+                    let b = self.read(instruction, 1)?;
+                    let value = match (opcode, a, b) {
+                        (Opcode::Add, Some(a), Some(b)) => Some(a + b),
+                        (Opcode::Mul, Some(a), Some(b)) => Some(a * b),
+                        (Opcode::Eq, Some(a), Some(b)) if a == b => Some(Poly::constant(1)),
+                        (Opcode::Lt, Some(a), Some(b)) if a == b => Some(Poly::constant(0)),
+                        (_, Some(a), Some(b)) => match (a.as_constant(), b.as_constant()) {
+                            (Some(a), Some(b)) if opcode == Opcode::Lt => {
+                                Some(Poly::constant(i64::from(a < b)))
+                            }
+                            (Some(a), Some(b)) => Some(Poly::constant(i64::from(a == b))),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    self.write(instruction, 2, value)?;
+                }
+                Opcode::In => {
+                    let value = self.input.pop_front().map(Poly::constant);
+                    self.write(instruction, 0, value)?;
+                }
+                Opcode::Out => {
+                    let value = known(self.read(instruction, 0)?)?;
+                    self.ip = restore;
+                    return Ok(Output::Value(value));
+                }
+                Opcode::Jnz | Opcode::Jz => {
+                    let condition = self.read(instruction, 0)?.and_then(|c| c.as_constant());
+                    let condition = condition.ok_or(SymbolicError::SymbolicBranch { ip })?;
+                    if (condition != 0) == (opcode == Opcode::Jnz) {
+                        let target = self.read(instruction, 1)?.and_then(|t| t.as_constant());
+                        let target = target
+                            .filter(|&t| t >= 0)
+                            .ok_or(SymbolicError::SymbolicAddress { ip })?;
+                        self.ip = target as usize;
+                        continue;
+                    }
+                }
+                Opcode::Arb => {
+                    let offset = self.read(instruction, 0)?.and_then(|o| o.as_constant());
+                    let offset = offset.ok_or(SymbolicError::SymbolicAddress { ip })?;
+                    self.rb = self.rb.wrapping_add(offset);
+                }
+                Opcode::Hlt => {
+                    return Ok(Output::Halt(known(self.get(0).cloned())?));
+                }
+            }
+            self.ip = restore;
+        }
+    }
+}
+
+static ZERO: Poly = Poly {
+    terms: BTreeMap::new(),
+};
+
+/// Values within `cells`, in order, for which `poly` evaluates to `target`.
+///
+/// Solves directly for the last cell the polynomial is linear in and tries
+/// every combination of the others, in order, returning the first solution.
+/// Arithmetic wraps as it does on the machine, so the solved cell may have
+/// several solutions, tried smallest first. Cells the polynomial does not
+/// depend on take the first value of their range.
+pub fn solve(poly: &Poly, target: i64, cells: &[(usize, RangeInclusive<i64>)]) -> Option<Vec<i64>> {
+    let used = poly.cells();
+    let solved = cells
+        .iter()
+        .rposition(|(address, _)| poly.linear(*address).is_some());
+    let linear = solved.map(|i| (i, poly.linear(cells[i].0).unwrap()));
+    let mut values = cells
+        .iter()
+        .map(|(_, range)| *range.start())
+        .collect::<Vec<_>>();
+    let free = (0..cells.len())
+        .filter(|&i| Some(i) != solved && used.contains(&cells[i].0))
+        .collect::<Vec<_>>();
+    let value_of = |values: &[i64], address: usize| {
+        cells
+            .iter()
+            .position(|(a, _)| *a == address)
+            .map_or(0, |i| values[i])
+    };
+    let found = each(cells, &free, &mut values, &mut |values| match &linear {
+        Some((i, (coefficient, rest))) => {
+            let remainder = target.wrapping_sub(rest.eval(|a| value_of(values, a)));
+            divide(remainder, *coefficient, cells[*i].1.clone()).any(|value| {
+                values[*i] = value;
+                poly.eval(|a| value_of(values, a)) == target
+            })
+        }
+        None => poly.eval(|a| value_of(values, a)) == target,
+    });
+    if found {
+        Some(values)
+    } else {
+        None
+    }
+}
+
+/// The values within `range` that give `remainder` when multiplied by the
+/// nonzero `coefficient` with wrapping, smallest first.
+///
+/// With `coefficient` being `odd << shift`, a solution exists if the low
+/// `shift` bits of `remainder` are zero, and is unique modulo
+/// `2^(64 - shift)`.
+fn divide(
+    remainder: i64,
+    coefficient: i64,
+    range: RangeInclusive<i64>,
+) -> impl Iterator<Item = i64> {
+    let shift = coefficient.trailing_zeros();
+    let solvable = remainder.trailing_zeros() >= shift;
+    let odd = coefficient as u64 >> shift;
+    // Each step doubles the number of low bits in which `odd * inverse` is 1,
+    // starting from 3 since an odd square is 1 modulo 8.
+    let mut inverse = odd;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(odd.wrapping_mul(inverse)));
+    }
+    let value = (remainder as u64 >> shift).wrapping_mul(inverse);
+    let step = 1i128 << (64 - shift);
+    let (start, end) = (*range.start() as i128, *range.end() as i128);
+    let first = start + (value as i128 - start).rem_euclid(step);
+    iter::successors(Some(first), move |&value| Some(value + step))
+        .take_while(move |&value| solvable && value <= end)
+        .map(|value| value as i64)
+}
+
+/// Calls `f` on every combination of values of the cells at `free`, in
+/// order, until it returns true.
+fn each<F: FnMut(&mut [i64]) -> bool>(
+    cells: &[(usize, RangeInclusive<i64>)],
+    free: &[usize],
+    values: &mut Vec<i64>,
+    f: &mut F,
+) -> bool {
+    match free.split_first() {
+        None => f(values),
+        Some((&i, rest)) => cells[i].1.clone().any(|value| {
+            values[i] = value;
+            each(cells, rest, values, f)
+        }),
+    }
+}
+
+/// Runs `program` with every combination of values stored in `cells`,
+/// returning the first, in order, that halts with `target` at address 0.
+///
+/// The values of the first cell are dealt out between threads in turn.
+pub fn brute_force(
+    program: &[i64],
+    cells: &[(usize, RangeInclusive<i64>)],
+    target: i64,
+) -> Option<Vec<i64>> {
+    let firsts = match cells.first() {
+        Some((_, range)) => range.clone(),
+        None if halts_with(program, cells, &[], target) => return Some(vec![]),
+        None => return None,
+    };
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    // The value of the first cell in the earliest solution found so far.
+    let best = AtomicI64::new(i64::MAX);
+    let free = (1..cells.len()).collect::<Vec<_>>();
+    thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|thread| {
+                let (firsts, best, free) = (firsts.clone(), &best, &free);
+                scope.spawn(move || {
+                    let mut values = cells.iter().map(|(_, r)| *r.start()).collect::<Vec<_>>();
+                    for first in firsts.skip(thread).step_by(threads) {
+                        if first > best.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        values[0] = first;
+                        let found = each(cells, free, &mut values, &mut |values| {
+                            halts_with(program, cells, values, target)
+                        });
+                        if found {
+                            best.fetch_min(first, Ordering::Relaxed);
+                            return Some(values);
+                        }
+                    }
+                    None
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .min_by_key(|values| values[0])
+    })
+}
+
+fn halts_with(
+    program: &[i64],
+    cells: &[(usize, RangeInclusive<i64>)],
+    values: &[i64],
+    target: i64,
+) -> bool {
+    let mut machine = Machine::new(program);
+    for ((address, _), &value) in cells.iter().zip(values) {
+        machine.set(*address, value);
+    }
+    machine.limits.instructions = Some(STEP_LIMIT);
+    machine.run() == Ok(Output::Halt(target))
+}
+
+/// How `search` found its answer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Method {
+    /// By solving the polynomial the program computes.
+    Symbolic(Poly),
+    /// By trying combinations, since the program could not be run
+    /// symbolically.
+    BruteForce(SymbolicError),
+}
+
+/// Finds values within `cells`, in order, for which `program` halts with
+/// `target` at address 0.
+///
+/// A symbolic solution is checked by running the program on it, and the
+/// search falls back to `brute_force` if the check fails or there is no
+/// symbolic solution, since a symbolic run does not notice reads through
+/// symbolic addresses that would fault.
+pub fn search(
+    program: &[i64],
+    cells: &[(usize, RangeInclusive<i64>)],
+    target: i64,
+) -> Option<(Vec<i64>, Method)> {
+    let symbols = cells
+        .iter()
+        .map(|(address, _)| *address)
+        .collect::<Vec<_>>();
+    let mut machine = SymbolicMachine::new(program, &symbols);
+    let error = loop {
+        match machine.run() {
+            Ok(Output::Halt(poly)) => match solve(&poly, target, cells) {
+                Some(values) if halts_with(program, cells, &values, target) => {
+                    return Some((values, Method::Symbolic(poly)));
+                }
+                _ => break SymbolicError::NoSymbolicSolution,
+            },
+            Ok(Output::Value(_)) => continue,
+            Ok(Output::NeedsInput) => {
+                break machine.fault(|ip, opcode| IntcodeError::MissingInput { ip, opcode })
+            }
+            Err(error) => break error,
+        }
+    };
+    brute_force(program, cells, target).map(|values| (values, Method::BruteForce(error)))
+}
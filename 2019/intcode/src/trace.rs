@@ -0,0 +1,282 @@
+//! Execution traces in JSON Lines form, one `Event` per executed instruction.
+
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::machine::{Machine, Output};
+use crate::memory::Memory;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::io::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Operand {
+    /// Parameter mode digit: 0 position, 1 immediate, 2 relative.
+    pub mode: i64,
+    /// The raw parameter word.
+    pub param: i64,
+    /// Resolved address for position and relative parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<usize>,
+    /// Value read through the parameter; absent for write targets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Write {
+    pub address: usize,
+    pub value: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub ip: usize,
+    pub opcode: i64,
+    pub operands: Vec<Operand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write: Option<Write>,
+    /// Relative base after the instruction executed.
+    pub rb: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<i64>,
+}
+
+/// Executes one instruction and describes what it did.
+///
+/// The event is rebuilt from the machine state around `Machine::step`, so
+/// untraced runs pay nothing for tracing. No event is produced when the
+/// instruction could not execute (`NeedsInput`).
+pub fn step<M: Memory<Word = i64>>(
+    machine: &mut Machine<M>,
+) -> Result<(Option<Output>, Option<Event>), IntcodeError> {
+    let ip = machine.ip;
+    let opcode = machine.get(ip);
+    let input = machine.input.front().copied();
+    let instruction = match Instruction::decode(opcode) {
+        Ok(instruction) => instruction,
+        Err(_) => return machine.step().map(|output| (output, None)),
+    };
+    let write_param = instruction.opcode.write_param();
+    let operands = (0..instruction.opcode.params())
+        .map(|i| {
+            let mode = instruction.modes[i];
+            let param = machine.get(ip + 1 + i);
+            let address = resolve(mode, param, machine.rb);
+            Operand {
+                mode: mode.digit(),
+                param,
+                address,
+                value: match (mode, Some(i) == write_param) {
+                    (_, true) => None,
+                    (Mode::Immediate, _) => Some(param),
+                    _ => address.map(|a| machine.get(a)),
+                },
+            }
+        })
+        .collect::<Vec<Operand>>();
+    let output = machine.step()?;
+    if output == Some(Output::NeedsInput) {
+        return Ok((output, None));
+    }
+    let write = write_param
+        .and_then(|i| operands[i].address)
+        .map(|address| Write {
+            address,
+            value: machine.get(address),
+        });
+    let event = Event {
+        ip,
+        opcode,
+        operands,
+        write,
+        rb: machine.rb,
+        input: match instruction.opcode {
+            Opcode::In => input,
+            _ => None,
+        },
+        output: match output {
+            Some(Output::Value(v)) => Some(v),
+            _ => None,
+        },
+    };
+    Ok((output, Some(event)))
+}
+
+fn resolve(mode: Mode, param: i64, rb: i64) -> Option<usize> {
+    let address = match mode {
+        Mode::Position => param,
+        Mode::Relative => param.checked_add(rb)?,
+        Mode::Immediate => return None,
+    };
+    if address < 0 {
+        None
+    } else {
+        Some(address as usize)
+    }
+}
+
+/// Writes every executed instruction of a machine to `out` as JSON Lines.
+pub struct Recorder<W: io::Write> {
+    out: W,
+    error: Option<io::Error>,
+    pub events: u64,
+}
+
+impl<W: io::Write> Recorder<W> {
+    pub fn new(out: W) -> Recorder<W> {
+        Recorder {
+            out,
+            error: None,
+            events: 0,
+        }
+    }
+
+    /// Like `Machine::run`, recording each instruction along the way.
+    pub fn run<M: Memory<Word = i64>>(
+        &mut self,
+        machine: &mut Machine<M>,
+    ) -> Result<Output, IntcodeError> {
+        loop {
+            let (output, event) = step(machine)?;
+            if let Some(event) = event {
+                self.record(&event);
+            }
+            if let Some(output) = output {
+                return Ok(output);
+            }
+        }
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = serde_json::to_writer(&mut self.out, event)
+            .map_err(io::Error::from)
+            .and_then(|_| self.out.write_all(b"\n"));
+        match result {
+            Ok(()) => self.events += 1,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Flushes the trace, reporting the first write error encountered, if any.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reads events back from a JSON Lines trace.
+pub fn read<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<Event>> {
+    reader
+        .lines()
+        .filter(|line| line.as_ref().map_or(true, |l| !l.trim().is_empty()))
+        .map(|line| line.and_then(|l| serde_json::from_str(&l).map_err(io::Error::from)))
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Divergence {
+    /// The traces disagree at this zero-based event index.
+    Event {
+        index: u64,
+        left: Box<Event>,
+        right: Box<Event>,
+    },
+    /// One trace ended after `index` events while the other continued.
+    Length { index: u64, left_longer: bool },
+}
+
+/// Finds the first point at which two event streams differ.
+pub fn diff<A, B>(left: A, right: B) -> io::Result<Option<Divergence>>
+where
+    A: IntoIterator<Item = io::Result<Event>>,
+    B: IntoIterator<Item = io::Result<Event>>,
+{
+    let mut left = left.into_iter();
+    let mut right = right.into_iter();
+    let mut index = 0;
+    loop {
+        match (left.next().transpose()?, right.next().transpose()?) {
+            (None, None) => return Ok(None),
+            (Some(_), None) => {
+                return Ok(Some(Divergence::Length {
+                    index,
+                    left_longer: true,
+                }))
+            }
+            (None, Some(_)) => {
+                return Ok(Some(Divergence::Length {
+                    index,
+                    left_longer: false,
+                }))
+            }
+            (Some(l), Some(r)) if l != r => {
+                return Ok(Some(Divergence::Event {
+                    index,
+                    left: Box::new(l),
+                    right: Box::new(r),
+                }))
+            }
+            _ => index += 1,
+        }
+    }
+}
+
+/// Re-executes `machine`, feeding it the inputs recorded in `events`, and
+/// returns the first point where execution departs from the recording.
+///
+/// The left side of a reported divergence is the recording, the right side
+/// the replay. A recording that ends while the replay could still execute an
+/// instruction, such as a truncated trace file, is a divergence too; it must
+/// end where the program halted, starved for input or faulted.
+pub fn replay<M: Memory<Word = i64>, I>(
+    mut machine: Machine<M>,
+    events: I,
+) -> io::Result<Option<Divergence>>
+where
+    I: IntoIterator<Item = io::Result<Event>>,
+{
+    let mut count = 0;
+    let mut halted = false;
+    for (index, recorded) in events.into_iter().enumerate() {
+        let index = index as u64;
+        let recorded = recorded?;
+        if let Some(input) = recorded.input {
+            machine.input.push_back(input);
+        }
+        let executed = match step(&mut machine) {
+            Ok((_, Some(event))) => event,
+            Ok((_, None)) | Err(_) => {
+                return Ok(Some(Divergence::Length {
+                    index,
+                    left_longer: true,
+                }))
+            }
+        };
+        if executed != recorded {
+            return Ok(Some(Divergence::Event {
+                index,
+                left: Box::new(recorded),
+                right: Box::new(executed),
+            }));
+        }
+        count = index + 1;
+        halted = executed.opcode == Opcode::Hlt.code();
+    }
+    if halted {
+        return Ok(None);
+    }
+    match step(&mut machine) {
+        Ok((_, Some(_))) => Ok(Some(Divergence::Length {
+            index: count,
+            left_longer: false,
+        })),
+        Ok((_, None)) | Err(_) => Ok(None),
+    }
+}
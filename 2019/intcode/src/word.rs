@@ -0,0 +1,85 @@
+use num::{BigInt, ToPrimitive, Zero};
+use std::fmt;
+
+/// A value a machine can hold in memory and compute with.
+///
+/// Addresses, opcodes and relative base offsets are always narrowed to
+/// `i64`; wider words only widen the arithmetic.
+pub trait Word: Clone + PartialEq + PartialOrd + fmt::Debug + fmt::Display {
+    fn from_i64(value: i64) -> Self;
+    /// The value as an `i64`, if it fits.
+    fn to_i64(&self) -> Option<i64>;
+    fn is_zero(&self) -> bool;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+}
+
+macro_rules! primitive_word {
+    ($t:ty) => {
+        impl Word for $t {
+            fn from_i64(value: i64) -> $t {
+                value as $t
+            }
+
+            fn to_i64(&self) -> Option<i64> {
+                ToPrimitive::to_i64(self)
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn checked_add(&self, other: &$t) -> Option<$t> {
+                <$t>::checked_add(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &$t) -> Option<$t> {
+                <$t>::checked_mul(*self, *other)
+            }
+
+            fn wrapping_add(&self, other: &$t) -> $t {
+                <$t>::wrapping_add(*self, *other)
+            }
+
+            fn wrapping_mul(&self, other: &$t) -> $t {
+                <$t>::wrapping_mul(*self, *other)
+            }
+        }
+    };
+}
+
+primitive_word!(i64);
+primitive_word!(i128);
+
+/// Never overflows, so checked and wrapping arithmetic agree.
+impl Word for BigInt {
+    fn from_i64(value: i64) -> BigInt {
+        BigInt::from(value)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        ToPrimitive::to_i64(self)
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn checked_add(&self, other: &BigInt) -> Option<BigInt> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &BigInt) -> Option<BigInt> {
+        Some(self * other)
+    }
+
+    fn wrapping_add(&self, other: &BigInt) -> BigInt {
+        self + other
+    }
+
+    fn wrapping_mul(&self, other: &BigInt) -> BigInt {
+        self * other
+    }
+}
@@ -0,0 +1,83 @@
+use intcode::analysis::Analysis;
+use intcode::{Machine, Output};
+use std::collections::BTreeMap;
+
+/// Prints 1, then turns its first instruction into a halt and jumps to it.
+const PATCHING: &[i64] = &[
+    104, 1, // out #1
+    1101, 0, 99, 0, // add #0, #99, [0]
+    1105, 1, 0, // jnz #1, #0
+];
+
+/// Outputs a cell beyond the image, then overwrites its own opcode through
+/// the relative base.
+const WANDERING: &[i64] = &[
+    109, 3, // arb #3
+    4, 100, // out [100]
+    21101, 7, 0, 1, // add #7, #0, [rb+1]
+    99,
+];
+
+fn analyse(program: &[i64]) -> Analysis {
+    let mut analysis = Analysis::new(program);
+    let mut machine = Machine::new(program);
+    while let Ok(Output::Value(_)) = analysis.run(&mut machine) {}
+    analysis
+}
+
+#[test]
+fn fixed_writes_into_code_are_found_statically() {
+    let analysis = Analysis::new(PATCHING);
+    assert_eq!(analysis.reachable.iter().collect::<Vec<_>>(), [&0, &2, &6]);
+    assert_eq!(analysis.static_patches, [(2, 0)]);
+    assert_eq!(analysis.owner(4), Some(2));
+    assert_eq!(analysis.owner(9), None);
+    assert!(Analysis::new(WANDERING).static_patches.is_empty());
+}
+
+#[test]
+fn patched_instructions_are_counted() {
+    let analysis = analyse(PATCHING);
+    assert_eq!(
+        analysis.executed,
+        [(0, 2), (2, 1), (6, 1)].iter().cloned().collect()
+    );
+    assert_eq!(analysis.patched, [(0, 1)].iter().cloned().collect());
+    assert_eq!(analysis.code_writes(), [(2, 0, 0)]);
+    assert!(analysis.uninitialized.is_empty());
+}
+
+#[test]
+fn runtime_writes_and_uninitialized_reads() {
+    let analysis = analyse(WANDERING);
+    assert_eq!(analysis.uninitialized, [(100, 2)].iter().cloned().collect());
+    assert_eq!(analysis.code_writes(), [(4, 4, 4)]);
+    assert_eq!(analysis.written.keys().collect::<Vec<_>>(), [&4]);
+    assert!(analysis.patched.is_empty());
+}
+
+#[test]
+fn written_cells_are_not_uninitialized() {
+    let program = [
+        1101, 1, 2, 50, // add #1, #2, [50]
+        4, 50, // out [50]
+        99,
+    ];
+    let analysis = analyse(&program);
+    assert_eq!(analysis.uninitialized, BTreeMap::new());
+    assert_eq!(analysis.written.keys().collect::<Vec<_>>(), [&50]);
+}
+
+#[test]
+fn report_sections() {
+    let report = analyse(PATCHING).report(10);
+    for line in &[
+        "executed      4 instructions, 3 distinct, 0 not found statically",
+        "static patches: 1 fixed writes into reachable code",
+        "patched code: 1 instructions executed after being modified",
+        "code writes: 1 writes into instructions",
+        "uninitialized reads: 0 cells beyond the image read before being written",
+    ] {
+        assert!(report.lines().any(|l| l == *line), "{}\n{}", line, report);
+    }
+}
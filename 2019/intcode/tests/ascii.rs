@@ -0,0 +1,84 @@
+use intcode::ascii::{AsciiMachine, Event, Transcript};
+use intcode::IntcodeError;
+use std::io::{self, Write};
+
+/// Prints "Hi", echoes one line of input, then answers 1000 and halts.
+const ECHO: [i64; 20] = [
+    104, 72, 104, 105, 104, 10, // out 'H', 'i', '\n'
+    3, 100, 4, 100, // in [100], out [100]
+    1008, 100, 10, 101, 1006, 101, 6, // loop until a newline went by
+    104, 1000, 99,
+];
+
+/// A writer whose reader has gone away.
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn lines_answers_and_input() {
+    let mut machine = AsciiMachine::new(&ECHO);
+    assert_eq!(machine.next_event(), Ok(Event::Line("Hi".to_string())));
+    assert_eq!(machine.next_event(), Ok(Event::NeedsInput));
+    machine.send_line("ok");
+    let transcript = machine.run().unwrap();
+    assert_eq!(
+        transcript,
+        Transcript {
+            lines: vec!["ok".to_string()],
+            answers: vec![1000],
+            halted: true,
+        }
+    );
+}
+
+#[test]
+fn unfinished_line_comes_before_what_stopped_it() {
+    let mut machine = AsciiMachine::new(&[104, 62, 3, 0, 104, 120, 104, 1000, 99]);
+    assert_eq!(machine.next_event(), Ok(Event::Line(">".to_string())));
+    assert_eq!(machine.next_event(), Ok(Event::NeedsInput));
+    machine.send_line("");
+    assert_eq!(machine.next_event(), Ok(Event::Line("x".to_string())));
+    assert_eq!(machine.next_event(), Ok(Event::Answer(1000)));
+    assert_eq!(machine.next_event(), Ok(Event::Halt));
+}
+
+#[test]
+fn interact_reads_lines_and_echoes_output() {
+    let mut machine = AsciiMachine::new(&ECHO);
+    let mut output = vec![];
+    assert_eq!(
+        machine.interact(&b"hello\r\n"[..], &mut output),
+        Ok(Event::Halt)
+    );
+    assert_eq!(String::from_utf8(output).unwrap(), "Hi\nhello\n1000\n");
+
+    let mut machine = AsciiMachine::new(&ECHO);
+    let mut output = vec![];
+    assert_eq!(
+        machine.interact(&b""[..], &mut output),
+        Ok(Event::NeedsInput)
+    );
+    assert_eq!(output, b"Hi\n");
+}
+
+#[test]
+fn interact_reports_failed_writes() {
+    let mut machine = AsciiMachine::new(&ECHO);
+    assert_eq!(
+        machine.interact(&b"hello\n"[..], BrokenPipe),
+        Err(IntcodeError::Device {
+            ip: 6,
+            opcode: 3,
+            message: "pipe closed".to_string(),
+        })
+    );
+}
@@ -0,0 +1,170 @@
+use intcode::asm::{self, AsmError};
+use intcode::{disasm, Machine};
+
+fn run(program: &[i64], input: &[i64]) -> Vec<i64> {
+    let mut machine = Machine::new(program);
+    machine.input.extend(input);
+    machine.run_to_halt().unwrap()
+}
+
+/// The text of each code line in the disassembly of `program`.
+fn code(program: &[i64]) -> Vec<String> {
+    disasm::disassemble(program)
+        .into_iter()
+        .filter(|line| line.kind == disasm::Kind::Code)
+        .map(|line| line.text)
+        .collect()
+}
+
+fn error(line: usize, message: &str) -> Result<Vec<i64>, AsmError> {
+    Err(AsmError {
+        line,
+        message: message.to_string(),
+    })
+}
+
+#[test]
+fn labels_and_offsets() {
+    let source = "
+        in [count]
+    loop:
+        out [count]
+        add [count], #-1, [count]
+        jnz [count], #loop ; labels may be used before or after they are defined
+        hlt
+    count: db 0
+    ";
+    let program = asm::assemble(source).unwrap();
+    assert_eq!(
+        program,
+        [3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0]
+    );
+    assert_eq!(run(&program, &[3]), [3, 2, 1]);
+    assert_eq!(
+        code(&program),
+        [
+            "in [12]",
+            "out [12]",
+            "add [12], #-1, [12]",
+            "jnz [12], #2",
+            "hlt"
+        ]
+    );
+
+    let program = asm::assemble("out [end-1]\nout [end+0]\nhlt\ndb 7\nend: db 8").unwrap();
+    assert_eq!(run(&program, &[]), [7, 8]);
+}
+
+#[test]
+fn strings() {
+    let source = r#"
+        arb #text
+    loop:
+        jz [rb+0], #done
+        out [rb+0]
+        arb #1
+        jmp loop
+    done:
+        hlt
+    text: db "hi; \"there\"\n", 0
+    "#;
+    let program = asm::assemble(source).unwrap();
+    let output = run(&program, &[]);
+    assert_eq!(
+        String::from_utf8(output.iter().map(|&c| c as u8).collect()).unwrap(),
+        "hi; \"there\"\n"
+    );
+    assert_eq!(
+        code(&program),
+        [
+            "arb #13",
+            "jz [rb+0], #12",
+            "out [rb+0]",
+            "arb #1",
+            "jnz #1, #2",
+            "hlt"
+        ]
+    );
+}
+
+#[test]
+fn stack_macros() {
+    let source = "
+        arb #stack
+        push #21
+        call double
+        pop [result]
+        out [result]
+        hlt
+    double:
+        mul [rb-2], #2, [rb-2]  ; the argument sits below the return address
+        ret
+    result: db 0
+    stack:
+    ";
+    let program = asm::assemble(source).unwrap();
+    assert_eq!(program.len(), 36);
+    assert_eq!(run(&program, &[]), [42]);
+    assert_eq!(
+        code(&program),
+        [
+            "arb #36",
+            "add #21, #0, [rb+0]",
+            "arb #1",
+            "add #17, #0, [rb+0]",
+            "arb #1",
+            "jnz #1, #26",
+            "arb #-1",
+            "add [rb+0], #0, [35]",
+            "out [35]",
+            "hlt",
+            "mul [rb-2], #2, [rb-2]",
+            "arb #-1",
+            "jz #0, [rb+0]",
+        ]
+    );
+}
+
+#[test]
+fn bad_operands() {
+    assert_eq!(
+        asm::assemble("add #é, #1, [0]"),
+        error(1, "expected a number or label, found `é`")
+    );
+    assert_eq!(
+        asm::assemble("hlt\nout 5"),
+        error(2, "expected `#imm`, `[addr]` or `[rb+off]`, found `5`")
+    );
+    assert_eq!(
+        asm::assemble("add #1, #2, #3"),
+        error(1, "`add` cannot write to an immediate")
+    );
+    assert_eq!(
+        asm::assemble("out [x+y]"),
+        error(1, "invalid offset in `x+y`")
+    );
+    assert_eq!(
+        asm::assemble("out"),
+        error(1, "`out` takes 1 operand(s), found 0")
+    );
+    assert_eq!(
+        asm::assemble("jmp nowhere"),
+        error(1, "undefined label `nowhere`")
+    );
+    assert_eq!(
+        asm::assemble("hlt\na: out [a+9223372036854775807]"),
+        error(2, "`a+9223372036854775807` is out of range")
+    );
+    assert_eq!(
+        asm::assemble("a: hlt\na: hlt"),
+        error(2, "duplicate label `a`")
+    );
+    assert_eq!(
+        asm::assemble("db \"\\q\""),
+        error(1, "invalid escape `\\q`")
+    );
+    assert_eq!(
+        asm::assemble("frob #1"),
+        error(1, "unknown mnemonic `frob`")
+    );
+}
@@ -0,0 +1,62 @@
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::future::join_all;
+use futures::stream;
+use intcode::{IntcodeError, Machine};
+
+/// The second day 7 example, a feedback loop of five amplifiers.
+const FEEDBACK: &[i64] = &[
+    3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28, 1005,
+    28, 6, 99, 0, 0, 5,
+];
+
+#[test]
+fn feedback_loop_on_one_thread() {
+    let mut amplifiers = [9, 8, 7, 6, 5]
+        .iter()
+        .map(|&phase| Machine::with_input(FEEDBACK, phase))
+        .collect::<Vec<Machine>>();
+    let (mut senders, mut receivers): (Vec<_>, Vec<_>) =
+        amplifiers.iter().map(|_| mpsc::unbounded()).unzip();
+    senders[0].unbounded_send(0).unwrap();
+    senders.rotate_left(1);
+    let runs = amplifiers
+        .iter_mut()
+        .zip(receivers.iter_mut())
+        .zip(senders.iter_mut())
+        .map(|((amplifier, input), output)| amplifier.run_async(input, output));
+    for result in block_on(join_all(runs)) {
+        assert_eq!(result, Ok(3));
+    }
+    assert_eq!(receivers[0].try_recv(), Ok(139629729));
+}
+
+#[test]
+fn end_of_input_is_missing_input() {
+    let mut machine = Machine::new(&[3, 5, 4, 5, 99, 0]);
+    let (mut sender, _receiver) = mpsc::unbounded();
+    assert_eq!(
+        block_on(machine.run_async(stream::empty(), &mut sender)),
+        Err(IntcodeError::MissingInput { ip: 0, opcode: 3 })
+    );
+}
+
+#[test]
+fn closed_output_stops_the_machine() {
+    // Echoes its input forever.
+    let mut machine = Machine::new(&[3, 7, 4, 7, 1105, 1, 0, 0]);
+    let (mut sender, receiver) = mpsc::unbounded();
+    drop(receiver);
+    assert_eq!(
+        block_on(machine.run_async(stream::iter(vec![1, 2, 3]), &mut sender)),
+        Err(IntcodeError::OutputClosed { ip: 2, opcode: 4 })
+    );
+    assert_eq!((machine.ip, machine.get(7)), (4, 1));
+}
+
+#[test]
+fn input_survives_a_faulting_read() {
+    let mut machine = Machine::with_input(&[3, -1, 99], 7);
+    assert!(machine.run().is_err());
+    assert_eq!(machine.input, [7]);
+}
@@ -0,0 +1,77 @@
+use intcode::cached::CachedMachine;
+use intcode::{Machine, Output};
+
+const DAY_05: &str = include_str!("../../05/input");
+const DAY_09: &str = include_str!("../../09/input");
+
+fn outputs(machine: &mut CachedMachine) -> Vec<i64> {
+    let mut outputs = vec![];
+    loop {
+        match machine.run() {
+            Ok(Output::Value(v)) => outputs.push(v),
+            Ok(Output::Halt(_)) => return outputs,
+            other => panic!("stopped early: {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn puzzle_inputs_match_the_machine() {
+    for &(source, input) in &[(DAY_05, 1), (DAY_05, 5), (DAY_09, 1), (DAY_09, 2)] {
+        let program = intcode::read_program(source.as_bytes()).unwrap();
+        let mut cached = CachedMachine::from(Machine::with_input(&program, input));
+        let expected = Machine::with_input(&program, input).run_to_halt().unwrap();
+        assert_eq!(outputs(&mut cached), expected);
+    }
+}
+
+#[test]
+fn self_modifying_code_is_decoded_again() {
+    let mut machine = CachedMachine::new(&[
+        104, 1, // out #1
+        1101, 0, 99, 0, // add #0, #99, [0]
+        1105, 1, 0, // jnz #1, #0
+    ]);
+    assert_eq!(machine.run(), Ok(Output::Value(1)));
+    assert_eq!(machine.run(), Ok(Output::Halt(99)));
+}
+
+#[test]
+fn set_invalidates_the_cache() {
+    // Outputs 1 forever.
+    let mut machine = CachedMachine::new(&[104, 1, 1105, 1, 0]);
+    assert_eq!(machine.run(), Ok(Output::Value(1)));
+    machine.set(0, 99);
+    assert_eq!(machine.run(), Ok(Output::Halt(99)));
+    assert_eq!(machine.machine().ip, 0);
+}
+
+#[test]
+fn writes_to_parameters_are_seen() {
+    let mut machine = CachedMachine::new(&[
+        4, 10, // out [10]
+        1101, 0, 11, 1, // add #0, #11, [1]
+        1105, 1, 0, // jnz #1, #0
+        99, 5, 6,
+    ]);
+    assert_eq!(machine.run(), Ok(Output::Value(5)));
+    assert_eq!(machine.run(), Ok(Output::Value(6)));
+}
+
+#[test]
+fn faults_match_the_machine() {
+    let programs: &[&[i64]] = &[
+        &[109, -1, 204, 0, 99], // relative address below zero
+        &[4, -1, 99],           // position address below zero
+        &[11101, 1, 1, 0, 99],  // immediate write target
+        &[1105, 1, -3],         // jump below zero
+        &[42],                  // invalid opcode
+        &[3, 0, 99],            // no input
+    ];
+    for program in programs {
+        let mut machine = Machine::new(program);
+        let mut cached = CachedMachine::new(program);
+        assert_eq!(cached.run(), machine.run(), "{:?}", program);
+        assert_eq!(cached.machine().ip, machine.ip, "{:?}", program);
+    }
+}
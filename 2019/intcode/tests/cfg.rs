@@ -0,0 +1,71 @@
+use intcode::cfg::{self, Edge, EdgeKind, Exit};
+
+const ARCADE: &str = include_str!("../../13/input");
+
+/// Calls a function at 10 that outputs 5, then halts.
+const CALL: [i64; 19] = [
+    109, 100, // arb #100
+    21101, 9, 0, 0, // add #9, #0, [rb+0]
+    1105, 1, 10, // jnz #1, #10
+    99, // hlt
+    109, 1, 104, 5, 109, -1, // arb #1, out #5, arb #-1
+    2106, 0, 0, // jz #0, [rb+0]
+];
+
+fn edge(from: usize, to: usize, kind: EdgeKind) -> Edge {
+    Edge { from, to, kind }
+}
+
+#[test]
+fn call_and_return() {
+    let graph = cfg::build(&CALL);
+    let blocks = graph
+        .blocks
+        .iter()
+        .map(|b| (b.start, b.end, b.exit))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        blocks,
+        [
+            (0, 9, Exit::Call),
+            (9, 10, Exit::Halt),
+            (10, 19, Exit::Return)
+        ]
+    );
+    let edges = graph.edges.iter().copied().collect::<Vec<_>>();
+    assert_eq!(
+        edges,
+        [
+            edge(0, 9, EdgeKind::Resume),
+            edge(0, 10, EdgeKind::Call),
+            edge(10, 9, EdgeKind::Return),
+        ]
+    );
+    let functions = graph
+        .functions
+        .iter()
+        .map(|f| (f.entry, f.blocks.iter().copied().collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    assert_eq!(functions, [(0, vec![0, 9]), (10, vec![10])]);
+}
+
+#[test]
+fn branches_fall_through_and_jump() {
+    // in [0]; jz [0], #9; out #1; hlt; db 0; out #0; hlt
+    let graph = cfg::build(&[3, 0, 1006, 0, 9, 104, 1, 99, 0, 104, 0, 99]);
+    assert_eq!(graph.blocks[0].exit, Exit::Branch);
+    assert!(graph.edges.contains(&edge(0, 5, EdgeKind::Next)));
+    assert!(graph.edges.contains(&edge(0, 9, EdgeKind::Jump)));
+    assert_eq!(graph.functions.len(), 1);
+}
+
+#[test]
+fn arcade_functions() {
+    let graph = cfg::build(&intcode::read_program(ARCADE.as_bytes()).unwrap());
+    let entries = graph.functions.iter().map(|f| f.entry).collect::<Vec<_>>();
+    assert_eq!(entries, [0, 393, 456, 549, 578, 601]);
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph cfg {"));
+    assert!(dot.contains("b601 -> b456 [style=bold];"));
+    assert!(dot.contains("b540 -> b630 [style=dashed];"));
+}
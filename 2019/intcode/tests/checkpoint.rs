@@ -0,0 +1,50 @@
+use intcode::{Machine, Memory, Output, PagedMemory};
+
+/// Adds each input to a running total at address 20 and outputs the total.
+const TOTAL: [i64; 11] = [3, 21, 1, 20, 21, 20, 4, 20, 1105, 1, 0];
+
+fn feed<M: Memory<Word = i64>>(machine: &mut Machine<M>, input: i64) -> Output {
+    machine.input.push_back(input);
+    machine.run().unwrap()
+}
+
+#[test]
+fn forks_run_independently() {
+    let mut machine = Machine::with_memory(PagedMemory::load(&TOTAL));
+    assert_eq!(feed(&mut machine, 5), Output::Value(5));
+    let mut fork = machine.clone();
+    assert_eq!(feed(&mut fork, 10), Output::Value(15));
+    assert_eq!(feed(&mut machine, 1), Output::Value(6));
+    assert_eq!(feed(&mut fork, 1), Output::Value(16));
+    assert_eq!((machine.get(20), fork.get(20)), (6, 16));
+}
+
+#[test]
+fn saved_machines_resume_where_they_stopped() {
+    let mut machine = Machine::new(&TOTAL);
+    feed(&mut machine, 5);
+    machine.input.extend(&[7, 8]);
+    let mut saved = vec![];
+    machine.save(&mut saved).unwrap();
+
+    let mut restored: Machine = Machine::restore(&saved[..]).unwrap();
+    assert_eq!((restored.ip, restored.rb), (machine.ip, machine.rb));
+    assert_eq!(restored.executed, machine.executed);
+    assert_eq!(restored.input, [7, 8]);
+    for expected in &[12, 20] {
+        assert_eq!(restored.run(), Ok(Output::Value(*expected)));
+        assert_eq!(machine.run(), Ok(Output::Value(*expected)));
+    }
+
+    let mut paged = Machine::with_memory(PagedMemory::load(&TOTAL));
+    feed(&mut paged, 3);
+    let mut saved = vec![];
+    paged.save(&mut saved).unwrap();
+    let mut restored: Machine<PagedMemory> = Machine::restore(&saved[..]).unwrap();
+    assert_eq!(feed(&mut restored, 4), Output::Value(7));
+}
+
+#[test]
+fn restore_rejects_garbage() {
+    assert!(Machine::<PagedMemory>::restore(&b"{\"ip\": 0}"[..]).is_err());
+}
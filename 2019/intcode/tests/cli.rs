@@ -0,0 +1,42 @@
+use clap::{App, Arg, ArgMatches};
+use intcode::{cli, Machine};
+
+fn matches(args: &[&str]) -> ArgMatches<'static> {
+    App::new("test")
+        .arg(Arg::from_usage("--poke [ADDR=VAL]... 'poke'").number_of_values(1))
+        .arg(Arg::from_usage("--input [LIST]... 'input'").number_of_values(1))
+        .arg(Arg::from_usage("--ascii [TEXT]... 'ascii'").number_of_values(1))
+        .get_matches_from(std::iter::once("test").chain(args.iter().cloned()))
+}
+
+#[test]
+fn input_keeps_command_line_order() {
+    let matches = matches(&["--input", "1, 2", "--ascii", "a\\n", "--input", "3"]);
+    assert_eq!(cli::input(&matches), Ok(vec![1, 2, 97, 10, 3]));
+    assert_eq!(cli::input(&self::matches(&[])), Ok(vec![]));
+    assert!(cli::input(&self::matches(&["--input", "1,x"])).is_err());
+}
+
+#[test]
+fn pokes_patch_memory() {
+    let mut machine = Machine::new(&[1, 2, 3]);
+    assert_eq!(
+        cli::poke(
+            &mut machine,
+            &matches(&["--poke", "1=5", "--poke", "10 = -1"]),
+        ),
+        Ok(vec![1, 10])
+    );
+    assert_eq!((machine.get(1), machine.get(10)), (5, -1));
+    assert!(cli::poke(&mut machine, &matches(&["--poke", "1"])).is_err());
+    assert!(cli::poke(&mut machine, &matches(&["--poke=-1=2"])).is_err());
+}
+
+#[test]
+fn pokes_respect_the_memory_limit() {
+    let mut machine = Machine::new(&[1, 2, 3]);
+    machine.limits.memory = Some(8);
+    assert!(cli::poke(&mut machine, &matches(&["--poke", "7=1"])).is_ok());
+    assert!(cli::poke(&mut machine, &matches(&["--poke", "8=1"])).is_err());
+    assert_eq!(machine.get(8), 0);
+}
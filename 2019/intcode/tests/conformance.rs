@@ -0,0 +1,133 @@
+//! The example programs from the puzzle texts, with the results the puzzles
+//! give for them, plus the day 2 program kept in `05/02_input`.
+
+use intcode::{Machine, Output};
+
+const JUMP_POSITION: &str = include_str!("../../05/sample1");
+const JUMP_IMMEDIATE: &str = include_str!("../../05/sample2");
+const COMPARE_TO_EIGHT: &str = include_str!("../../05/sample3");
+const GRAVITY_ASSIST: &str = include_str!("../../05/02_input");
+const QUINE: &str = include_str!("../../09/sample1");
+const SIXTEEN_DIGITS: &str = include_str!("../../09/sample2");
+const LARGE_NUMBER: &str = include_str!("../../09/sample3");
+
+fn load(source: &str) -> Vec<i64> {
+    intcode::read_program(source.as_bytes()).unwrap()
+}
+
+/// Runs `program` to completion on `input` and returns its output.
+fn run(program: &[i64], input: &[i64]) -> Vec<i64> {
+    let mut machine = Machine::new(program);
+    machine.input.extend(input);
+    machine.run_to_halt().unwrap()
+}
+
+/// Runs `program` to completion and returns the memory it was loaded into.
+fn memory_after(program: &[i64]) -> Vec<i64> {
+    let mut machine = Machine::new(program);
+    machine.run_to_halt().unwrap();
+    (0..program.len()).map(|i| machine.get(i)).collect()
+}
+
+#[test]
+fn day02_examples() {
+    let examples: &[(&[i64], &[i64])] = &[
+        (
+            &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+            &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50],
+        ),
+        (&[1, 0, 0, 0, 99], &[2, 0, 0, 0, 99]),
+        (&[2, 3, 0, 3, 99], &[2, 3, 0, 6, 99]),
+        (&[2, 4, 4, 5, 99, 0], &[2, 4, 4, 5, 99, 9801]),
+        (
+            &[1, 1, 1, 4, 99, 5, 6, 0, 99],
+            &[30, 1, 1, 4, 2, 5, 6, 0, 99],
+        ),
+    ];
+    for &(program, expected) in examples {
+        assert_eq!(memory_after(program), expected, "{:?}", program);
+    }
+}
+
+#[test]
+fn day02_program_halts_with_address_zero() {
+    let program = load(GRAVITY_ASSIST);
+    for &(noun, verb, expected) in &[(12, 2, 11590668), (22, 54, 19690720)] {
+        let mut machine = Machine::new(&program);
+        machine.set(1, noun);
+        machine.set(2, verb);
+        assert_eq!(machine.run(), Ok(Output::Halt(expected)));
+    }
+}
+
+#[test]
+fn day05_echo_and_parameter_modes() {
+    for &input in &[-7, 0, 42] {
+        assert_eq!(run(&[3, 0, 4, 0, 99], &[input]), [input]);
+    }
+    assert_eq!(memory_after(&[1002, 4, 3, 4, 33]), [1002, 4, 3, 4, 99]);
+    assert_eq!(memory_after(&[1101, 100, -1, 4, 0]), [1101, 100, -1, 4, 99]);
+}
+
+#[test]
+fn day05_comparisons() {
+    let equal_position = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+    let less_position = [3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+    let equal_immediate = [3, 3, 1108, -1, 8, 3, 4, 3, 99];
+    let less_immediate = [3, 3, 1107, -1, 8, 3, 4, 3, 99];
+    for &input in &[-8, 7, 8, 9] {
+        let equal = vec![i64::from(input == 8)];
+        let less = vec![i64::from(input < 8)];
+        assert_eq!(run(&equal_position, &[input]), equal);
+        assert_eq!(run(&less_position, &[input]), less);
+        assert_eq!(run(&equal_immediate, &[input]), equal);
+        assert_eq!(run(&less_immediate, &[input]), less);
+    }
+}
+
+#[test]
+fn day05_jumps() {
+    for source in &[JUMP_POSITION, JUMP_IMMEDIATE] {
+        let program = load(source);
+        for &input in &[-5, 0, 1, 42] {
+            assert_eq!(run(&program, &[input]), [i64::from(input != 0)]);
+        }
+    }
+}
+
+#[test]
+fn day05_compare_to_eight() {
+    let program = load(COMPARE_TO_EIGHT);
+    for &(input, expected) in &[(-1, 999), (7, 999), (8, 1000), (9, 1001), (100, 1001)] {
+        assert_eq!(run(&program, &[input]), [expected]);
+    }
+}
+
+#[test]
+fn day09_quine() {
+    let program = load(QUINE);
+    assert_eq!(run(&program, &[]), program);
+}
+
+#[test]
+fn day09_sixteen_digit_output() {
+    let output = run(&load(SIXTEEN_DIGITS), &[]);
+    assert_eq!(output, [1219070632396864]);
+    assert_eq!(output[0].to_string().len(), 16);
+}
+
+#[test]
+fn day09_large_number() {
+    assert_eq!(run(&load(LARGE_NUMBER), &[]), [1125899906842624]);
+}
+
+#[test]
+fn day09_relative_base() {
+    // From the puzzle text: with rb at 2000, `arb #19` then `out [rb-34]`
+    // reads address 1985.
+    let mut machine = Machine::new(&[109, 19, 204, -34, 99]);
+    machine.rb = 2000;
+    machine.set(1985, 77);
+    assert_eq!(machine.run(), Ok(Output::Value(77)));
+    assert_eq!(machine.rb, 2019);
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Reads a value, doubles it and outputs the result.
+const DOUBLE: &str = "3,9,1002,9,2,9,4,9,99,0";
+
+fn debug(name: &str, args: &[&str], session: &str) -> Output {
+    debug_program(name, DOUBLE, args, session)
+}
+
+fn debug_program(name: &str, program: &str, args: &[&str], session: &str) -> Output {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, program).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_intcode-dbg"))
+        .args(args)
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(session.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn breakpoints_and_history() {
+    let output = debug(
+        "dbg-session.ic",
+        &["--input", "21"],
+        "b 6\nc\np mem[9]\nh 2\nc\n",
+    );
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.contains("breakpoint at 6"), "{}", text);
+    assert!(text.contains("mem[9] = 42"), "{}", text);
+    assert!(
+        text.contains("     2  rb 0       mul [9], #2, [9]"),
+        "{}",
+        text
+    );
+    assert!(text.contains("output: 42"), "{}", text);
+    assert!(text.contains("halted, mem[0] = 3"), "{}", text);
+}
+
+#[test]
+fn history_can_be_disabled() {
+    let output = debug(
+        "dbg-no-history.ic",
+        &["--input", "1", "--history", "0"],
+        "s 3\nh\n",
+    );
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.contains("history is disabled"), "{}", text);
+    assert!(!text.contains("rb 0"), "{}", text);
+}
+
+#[test]
+fn faulting_instructions_are_not_recorded() {
+    // The add at 0 succeeds, the add at 4 writes to address -5.
+    let output = debug_program("dbg-fault.ic", "1101,2,3,9,1,0,0,-5,99,0", &[], "s 2\nh\n");
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.contains("error: "), "{}", text);
+    assert!(text.contains("     0  rb 0 "), "{}", text);
+    assert!(!text.contains("     4  rb 0 "), "{}", text);
+}
+
+#[test]
+fn bad_arguments_are_reported() {
+    for args in &[["--input", "1,x"], ["--history", "lots"]] {
+        let output = debug("dbg-bad-args.ic", args, "");
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+        let error = String::from_utf8_lossy(&output.stderr);
+        assert!(error.contains("Invalid value"), "{}", error);
+        assert!(!error.contains("panicked"), "{}", error);
+    }
+}
@@ -0,0 +1,123 @@
+use intcode::decompile;
+use intcode::Machine;
+
+const INPUTS: [(&str, &str); 7] = [
+    ("02", include_str!("../../02/input")),
+    ("05", include_str!("../../05/input")),
+    ("07", include_str!("../../07/input")),
+    ("09", include_str!("../../09/input")),
+    ("13", include_str!("../../13/input")),
+    ("15", include_str!("../../15/input")),
+    ("17", include_str!("../../17/input")),
+];
+
+fn load(source: &str) -> Vec<i64> {
+    intcode::read_program(source.as_bytes()).unwrap()
+}
+
+fn run(program: &[i64], input: i64) -> Vec<i64> {
+    Machine::with_input(program, input).run_to_halt().unwrap()
+}
+
+#[test]
+fn puzzle_inputs_recompile_to_themselves() {
+    for &(day, source) in &INPUTS {
+        let program = load(source);
+        let decompiled = decompile::decompile(&program);
+        assert_eq!(decompiled.recompile(), program, "day {}", day);
+        assert!(decompiled.render().contains("fn main() {"), "day {}", day);
+    }
+}
+
+#[test]
+fn recompiled_programs_run_the_same() {
+    for &(source, input) in &[(INPUTS[1].1, 5), (INPUTS[3].1, 1)] {
+        let program = load(source);
+        let recompiled = decompile::decompile(&program).recompile();
+        assert_eq!(run(&recompiled, input), run(&program, input));
+    }
+}
+
+#[test]
+fn if_else_and_loop() {
+    let program = [
+        3, 100, // in [100]
+        1007, 100, 10, 101, // lt [100], #10, [101]
+        1006, 101, 14, // jz [101], #14
+        104, 1, // out #1
+        1105, 1, 16, // jnz #1, #16
+        104, 2, // out #2
+        101, -1, 100, 100, // add #-1, [100], [100]
+        1005, 100, 16, // jnz [100], #16
+        99,
+    ];
+    let expected = "\
+// globals, by how often the code uses them:
+//   g100: 5
+
+fn main() {
+    g100 = input();
+    if (g100 < 10) {
+        output(1);
+    } else {
+        output(2);
+    }
+    do {
+        g100 = g100 - 1;
+    } while (g100 != 0);
+    halt;
+}
+";
+    let decompiled = decompile::decompile(&program);
+    assert_eq!(decompiled.render(), expected);
+    assert_eq!(decompiled.recompile()[..program.len()], program);
+}
+
+#[test]
+fn calls_and_frames() {
+    let program = [
+        109, 100, // arb #100
+        21101, 5, 0, 1, // add #5, #0, [rb+1]
+        21101, 13, 0, 0, // add #13, #0, [rb+0]
+        1105, 1, 14, // jnz #1, #14
+        99, // hlt
+        109, 2, // arb #2
+        22101, 1, -1, -1, // add #1, [rb-1], [rb-1]
+        204, -1, // out [rb-1]
+        109, -2, // arb #-2
+        2106, 0, 0, // jz #0, [rb+0]
+    ];
+    let expected = "
+fn main() {
+    f14(5);
+    halt;
+}
+
+// frame size 2
+fn f14(a1) {
+    a1 = 1 + a1;
+    output(a1);
+    return;
+}
+";
+    let decompiled = decompile::decompile(&program);
+    assert_eq!(decompiled.render(), expected);
+    assert_eq!(decompiled.recompile(), program);
+    assert_eq!(run(&program, 0), [6]);
+}
+
+#[test]
+fn return_address_pushed_as_a_product() {
+    let program = [
+        109, 30, // arb #30
+        21102, 3, 3, 0, // mul #3, #3, [rb+0]
+        1105, 1, 10, // jnz #1, #10
+        99, // hlt
+        104, 7, // out #7
+        2106, 0, 0, // jz #0, [rb+0]
+    ];
+    let decompiled = decompile::decompile(&program);
+    assert!(decompiled.render().contains("    f10();\n"));
+    assert_eq!(decompiled.recompile(), program);
+    assert_eq!(run(&decompiled.recompile(), 0), [7]);
+}
@@ -0,0 +1,306 @@
+//! Differential tests: random programs must run the same on every engine.
+//!
+//! Programs are sequences of validly encoded instructions with small
+//! addresses, followed by a little data; what they do with them, including
+//! failing, is up to chance. Each engine executes the program one
+//! instruction at a time in lockstep with the reference `Machine`, and every
+//! step's result, `ip`, `rb` and finally memory have to agree.
+//!
+//! A run is cut short after `STEPS` instructions, or before a write beyond
+//! `ADDRESS_LIMIT`, which dense memory would have to allocate up to. The
+//! `aot` engine is not covered since each program would need compiling as
+//! Rust first; day 09's build script compiles the day 05 and day 09 puzzle
+//! inputs instead, and `09/puzzle/tests/compiled.rs` compares those.
+
+use intcode::analysis::Analysis;
+use intcode::cached::CachedMachine;
+use intcode::profile::Profile;
+use intcode::{
+    trace, Instruction, IntcodeError, Machine, Memory, Mode, Opcode, Output, PagedMemory,
+    VecMemory, Word,
+};
+use num::BigInt;
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+
+const STEPS: usize = 1000;
+const ADDRESS_LIMIT: usize = 1 << 16;
+/// Memory compared once a run is over.
+const COMPARED: usize = 1024;
+
+type Step = Result<Option<Output>, IntcodeError>;
+
+/// One way of executing a program.
+trait Engine {
+    fn step(&mut self) -> Step;
+    fn push_input(&mut self, value: i64);
+    fn registers(&self) -> (usize, i64);
+    fn read(&self, address: usize) -> i64;
+}
+
+impl<M: Memory<Word = i64>> Engine for Machine<M> {
+    fn step(&mut self) -> Step {
+        Machine::step(self)
+    }
+
+    fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    fn registers(&self) -> (usize, i64) {
+        (self.ip, self.rb)
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.get(address)
+    }
+}
+
+impl Engine for CachedMachine {
+    fn step(&mut self) -> Step {
+        CachedMachine::step(self)
+    }
+
+    fn push_input(&mut self, value: i64) {
+        self.input().push_back(value);
+    }
+
+    fn registers(&self) -> (usize, i64) {
+        (self.machine().ip, self.machine().rb)
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.get(address)
+    }
+}
+
+/// Implements `Engine` for a wrapper around a machine in its field `machine`.
+macro_rules! wrapped_engine {
+    ($t:ty, |$engine:ident| $step:expr) => {
+        impl Engine for $t {
+            fn step(&mut self) -> Step {
+                let $engine = self;
+                $step
+            }
+
+            fn push_input(&mut self, value: i64) {
+                self.machine.push_input(value);
+            }
+
+            fn registers(&self) -> (usize, i64) {
+                self.machine.registers()
+            }
+
+            fn read(&self, address: usize) -> i64 {
+                self.machine.read(address)
+            }
+        }
+    };
+}
+
+struct Traced {
+    machine: Machine,
+}
+
+wrapped_engine!(Traced, |e| trace::step(&mut e.machine)
+    .map(|(output, _)| output));
+
+struct Profiled {
+    profile: Profile,
+    machine: Machine,
+}
+
+wrapped_engine!(Profiled, |e| e.profile.step(&mut e.machine));
+
+struct Analysed {
+    analysis: Analysis,
+    machine: Machine,
+}
+
+wrapped_engine!(Analysed, |e| e.analysis.step(&mut e.machine));
+
+/// Saved and restored from JSON before every instruction.
+struct Checkpointed {
+    machine: Machine,
+}
+
+wrapped_engine!(Checkpointed, |e| {
+    let mut saved = vec![];
+    e.machine.save(&mut saved).unwrap();
+    e.machine = Machine::restore(&saved[..]).unwrap();
+    e.machine.step()
+});
+
+/// A machine over a wider word, only comparable while `i64` does not overflow.
+struct Wide<W: Word> {
+    machine: Machine<VecMemory<W>>,
+}
+
+impl<W: Word> Engine for Wide<W> {
+    fn step(&mut self) -> Step {
+        let narrow = |w: W| w.to_i64().unwrap();
+        self.machine.step().map(|output| {
+            output.map(|output| match output {
+                Output::Halt(v) => Output::Halt(narrow(v)),
+                Output::NeedsInput => Output::NeedsInput,
+                Output::Value(v) => Output::Value(narrow(v)),
+            })
+        })
+    }
+
+    fn push_input(&mut self, value: i64) {
+        self.machine.input.push_back(W::from_i64(value));
+    }
+
+    fn registers(&self) -> (usize, i64) {
+        (self.machine.ip, self.machine.rb)
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.machine.get(address).to_i64().unwrap()
+    }
+}
+
+/// The address the next instruction of `engine` writes to, if any.
+fn write_target(engine: &dyn Engine) -> Option<i64> {
+    let (ip, rb) = engine.registers();
+    let instruction = Instruction::decode(engine.read(ip)).ok()?;
+    let i = instruction.opcode.write_param()?;
+    let param = engine.read(ip + 1 + i);
+    match instruction.modes[i] {
+        Mode::Relative => param.checked_add(rb),
+        _ => Some(param),
+    }
+}
+
+/// Runs `engines` in lockstep against `reference`, feeding `input` whenever
+/// input is needed, until the program stops or one of the limits is hit.
+fn lockstep(
+    mut reference: Box<dyn Engine>,
+    mut engines: Vec<Box<dyn Engine>>,
+    input: &[i64],
+    stop_on_overflow: bool,
+) -> Result<(), TestCaseError> {
+    let mut input = input.iter();
+    for _ in 0..STEPS {
+        if write_target(&*reference).is_some_and(|a| a > ADDRESS_LIMIT as i64) {
+            break;
+        }
+        let expected = reference.step();
+        if stop_on_overflow {
+            if let Err(IntcodeError::Overflow { .. }) = expected {
+                return Ok(());
+            }
+        }
+        for (i, engine) in engines.iter_mut().enumerate() {
+            prop_assert_eq!(&engine.step(), &expected, "engine {}", i);
+            prop_assert_eq!(engine.registers(), reference.registers(), "engine {}", i);
+        }
+        match expected {
+            Ok(Some(Output::NeedsInput)) => match input.next() {
+                Some(&value) => {
+                    reference.push_input(value);
+                    for engine in engines.iter_mut() {
+                        engine.push_input(value);
+                    }
+                }
+                None => break,
+            },
+            Ok(Some(Output::Halt(_))) | Err(_) => break,
+            _ => {}
+        }
+    }
+    for address in 0..COMPARED {
+        for (i, engine) in engines.iter().enumerate() {
+            prop_assert_eq!(
+                engine.read(address),
+                reference.read(address),
+                "engine {} at {}",
+                i,
+                address
+            );
+        }
+    }
+    Ok(())
+}
+
+fn small() -> impl Strategy<Value = i64> {
+    -3i64..64
+}
+
+fn param() -> impl Strategy<Value = i64> {
+    prop_oneof![8 => small(), 1 => any::<i64>()]
+}
+
+fn mode() -> impl Strategy<Value = Mode> {
+    prop::sample::select(vec![Mode::Position, Mode::Immediate, Mode::Relative])
+}
+
+/// One validly encoded instruction with its parameters.
+fn instruction() -> impl Strategy<Value = Vec<i64>> {
+    let opcode = prop::sample::select(Opcode::ALL.to_vec());
+    let writes = prop::sample::select(vec![Mode::Position, Mode::Relative]);
+    (
+        opcode,
+        [mode(), mode(), mode()],
+        writes,
+        [param(), param(), param()],
+        small(),
+    )
+        .prop_map(|(opcode, mut modes, write_mode, mut params, write_param)| {
+            if let Some(i) = opcode.write_param() {
+                modes[i] = write_mode;
+                params[i] = write_param;
+            }
+            if opcode == Opcode::Hlt {
+                modes = [Mode::Position; 3];
+            }
+            let instruction = Instruction { opcode, modes };
+            let mut words = vec![instruction.encode()];
+            words.extend(&params[..opcode.params()]);
+            words
+        })
+}
+
+fn program() -> impl Strategy<Value = Vec<i64>> {
+    (
+        prop::collection::vec(instruction(), 1..24),
+        prop::collection::vec(small(), 0..8),
+    )
+        .prop_map(|(code, data)| code.concat().into_iter().chain(data).collect())
+}
+
+fn input() -> impl Strategy<Value = Vec<i64>> {
+    prop::collection::vec(small(), 0..6)
+}
+
+proptest! {
+    #[test]
+    fn engines_agree(program in program(), input in input()) {
+        let engines: Vec<Box<dyn Engine>> = vec![
+            Box::new(Machine::with_memory(PagedMemory::load(&program))),
+            Box::new(Machine::with_memory(BTreeMap::load(&program))),
+            Box::new(CachedMachine::new(&program)),
+            Box::new(Traced { machine: Machine::new(&program) }),
+            Box::new(Profiled { profile: Profile::new(), machine: Machine::new(&program) }),
+            Box::new(Analysed { analysis: Analysis::new(&program), machine: Machine::new(&program) }),
+            Box::new(Checkpointed { machine: Machine::new(&program) }),
+        ];
+        lockstep(Box::new(Machine::new(&program)), engines, &input, false)?;
+    }
+
+    #[test]
+    fn wide_words_agree_until_overflow(program in program(), input in input()) {
+        let mut reference = Machine::new(&program);
+        reference.checked = true;
+        let engines: Vec<Box<dyn Engine>> = vec![
+            Box::new(Wide { machine: Machine::with_memory(VecMemory::<i128>::load(&widen(&program))) }),
+            Box::new(Wide { machine: Machine::with_memory(VecMemory::<BigInt>::load(&widen(&program))) }),
+        ];
+        lockstep(Box::new(reference), engines, &input, true)?;
+    }
+}
+
+fn widen<W: Word>(program: &[i64]) -> Vec<W> {
+    program.iter().map(|&v| W::from_i64(v)).collect()
+}
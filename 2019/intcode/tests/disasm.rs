@@ -0,0 +1,71 @@
+use intcode::disasm::{self, Kind};
+
+#[test]
+fn calls_returns_and_data() {
+    let program = [
+        109, 100, // arb #100
+        21101, 5, 0, 1, // add #5, #0, [rb+1]
+        21101, 13, 0, 0, // add #13, #0, [rb+0]
+        1105, 1, 14, // jnz #1, #14
+        99, // hlt
+        109, 2, // arb #2
+        22101, 1, -1, -1, // add #1, [rb-1], [rb-1]
+        204, -1, // out [rb-1]
+        109, -2, // arb #-2
+        2106, 0, 0, // jz #0, [rb+0]
+        7, 8,
+    ];
+    let expected = "     0  109,100                   arb #100
+     2  21101,5,0,1               add #5, #0, [rb+1]
+     6  21101,13,0,0              add #13, #0, [rb+0]
+    10  1105,1,14                 jnz #1, #14
+L13:
+    13  99                        hlt
+L14:
+    14  109,2                     arb #2
+    16  22101,1,-1,-1             add #1, [rb-1], [rb-1]
+    20  204,-1                    out [rb-1]
+    22  109,-2                    arb #-2
+    24  2106,0,0                  jz #0, [rb+0]
+    27                            db 7, 8
+";
+    assert_eq!(disasm::render(&disasm::disassemble(&program)), expected);
+}
+
+#[test]
+fn unreachable_words_are_data() {
+    // The jump is always taken, so the words after it only look like code.
+    let program = [1105, 1, 7, 104, 1, 99, 5, 4, 6, 99];
+    let lines = disasm::disassemble(&program);
+    let kinds = lines
+        .iter()
+        .map(|line| (line.address, line.kind, line.text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            (0, Kind::Code, "jnz #1, #7"),
+            (3, Kind::Data, "db 104, 1, 99, 5"),
+            (7, Kind::Code, "out [6]"),
+            (9, Kind::Code, "hlt"),
+        ]
+    );
+    assert!(lines[2].target);
+
+    let reach = disasm::reachability(&program);
+    assert_eq!(reach.code.into_iter().collect::<Vec<_>>(), [0, 7, 9]);
+    assert_eq!(reach.targets.into_iter().collect::<Vec<_>>(), [7]);
+}
+
+#[test]
+fn undecodable_words_are_described_as_data() {
+    let program = [1002, 4, 3, 4, 33, 42];
+    assert_eq!(
+        disasm::describe(|a| program[a], 0),
+        ("mul [4], #3, [4]".to_string(), 4)
+    );
+    assert_eq!(
+        disasm::describe(|a| program[a], 5),
+        ("db 42".to_string(), 1)
+    );
+}
@@ -0,0 +1,63 @@
+use intcode::{IntcodeError, Machine, Output};
+
+fn run(program: &[i64]) -> Result<Output, IntcodeError> {
+    Machine::new(program).run()
+}
+
+#[test]
+fn faults_carry_the_faulting_instruction() {
+    assert_eq!(
+        run(&[1101, 1, 1, 5, 42, 0]),
+        Err(IntcodeError::InvalidOpcode { ip: 4, opcode: 42 })
+    );
+    assert_eq!(
+        run(&[109, 3, 301, 0, 0, 0, 99]),
+        Err(IntcodeError::InvalidMode {
+            ip: 2,
+            opcode: 301,
+            mode: 3,
+            rb: 3,
+        })
+    );
+    assert_eq!(
+        run(&[109, -5, 204, 1, 99]),
+        Err(IntcodeError::NegativeAddress {
+            ip: 2,
+            opcode: 204,
+            mode: 2,
+            rb: -5,
+            address: -4,
+        })
+    );
+    assert_eq!(
+        run(&[11101, 1, 1, 0, 99]),
+        Err(IntcodeError::ImmediateWrite {
+            ip: 0,
+            opcode: 11101,
+            rb: 0,
+        })
+    );
+}
+
+#[test]
+fn faults_describe_themselves() {
+    let error = run(&[109, -5, 204, 1, 99]).unwrap_err();
+    assert_eq!((error.ip(), error.opcode()), (2, 204));
+    assert_eq!(
+        error.to_string(),
+        "negative address -4 (mode 2) in opcode 204 at ip 2 (rb -5)"
+    );
+    assert_eq!(
+        Machine::new(&[3, 0, 99]).run_to_halt(),
+        Err(IntcodeError::MissingInput { ip: 0, opcode: 3 })
+    );
+}
+
+#[test]
+fn a_faulting_machine_stays_put() {
+    let mut machine = Machine::new(&[1101, 1, 1, 5, 42, 0]);
+    assert!(machine.run().is_err());
+    assert_eq!((machine.ip, machine.get(5)), (4, 2));
+    assert!(machine.run().is_err());
+    assert_eq!(machine.ip, 4);
+}
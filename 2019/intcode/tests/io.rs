@@ -0,0 +1,127 @@
+use intcode::io::{Ascii, Channel, Closures, Fixed, Numeric};
+use intcode::{IntcodeError, Machine, Output};
+use std::io::{self, Write};
+use std::sync::mpsc;
+use std::thread;
+
+/// Outputs each input plus one until it reads a zero.
+const SUCCESSOR: [i64; 15] = [
+    3, 20, // in [20]
+    1006, 20, 14, // jz [20], #14
+    101, 1, 20, 20, // add #1, [20], [20]
+    4, 20, // out [20]
+    1105, 1, 0, // jnz #1, #0
+    99,
+];
+
+fn successor() -> Machine {
+    Machine::new(&SUCCESSOR)
+}
+
+/// A writer whose reader has gone away.
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn fixed_collects_output() {
+    let mut device = Fixed::new(vec![1, 41, 0]);
+    assert_eq!(successor().run_with(&mut device), Ok(Output::Halt(3)));
+    assert_eq!(device.output, [2, 42]);
+
+    let mut device = Fixed::new(vec![1]);
+    let mut machine = successor();
+    assert_eq!(machine.run_with(&mut device), Ok(Output::NeedsInput));
+    assert_eq!(device.output, [2]);
+    device.input.push_back(0);
+    assert_eq!(machine.run_with(&mut device), Ok(Output::Halt(3)));
+}
+
+#[test]
+fn queued_input_comes_first() {
+    let mut machine = successor();
+    machine.input.push_back(9);
+    let mut device = Fixed::new(vec![0]);
+    assert_eq!(machine.run_with(&mut device), Ok(Output::Halt(3)));
+    assert_eq!(device.output, [10]);
+}
+
+#[test]
+fn numeric_reads_and_writes_lines() {
+    let mut out = vec![];
+    let mut device = Numeric::new(&b"1\n\n  5 \n0\n"[..], &mut out);
+    assert_eq!(successor().run_with(&mut device), Ok(Output::Halt(3)));
+    assert_eq!(String::from_utf8(out).unwrap(), "2\n6\n");
+
+    let mut out = vec![];
+    let mut device = Numeric::new(&b"1"[..], &mut out);
+    assert_eq!(successor().run_with(&mut device), Ok(Output::NeedsInput));
+}
+
+#[test]
+fn device_errors_stop_the_machine() {
+    let mut out = vec![];
+    let mut device = Numeric::new(&b"1\nfive\n"[..], &mut out);
+    assert_eq!(
+        successor().run_with(&mut device),
+        Err(IntcodeError::Device {
+            ip: 0,
+            opcode: 3,
+            message: "expected a number, found `five`".to_string(),
+        })
+    );
+    assert_eq!(out, b"2\n");
+
+    let mut device = Ascii::new(&b"a"[..], BrokenPipe);
+    assert_eq!(
+        successor().run_with(&mut device),
+        Err(IntcodeError::Device {
+            ip: 9,
+            opcode: 4,
+            message: "pipe closed".to_string(),
+        })
+    );
+}
+
+#[test]
+fn ascii_sends_lines_as_character_codes() {
+    let mut out = vec![];
+    let mut device = Ascii::new(&b"ab"[..], &mut out);
+    let mut machine = successor();
+    assert_eq!(machine.run_with(&mut device), Ok(Output::NeedsInput));
+    // 'a' + 1, 'b' + 1 and '\n' + 1; non-ASCII output prints as a number.
+    machine.input.push_back(999);
+    machine.input.push_back(0);
+    assert_eq!(machine.run_with(&mut device), Ok(Output::Halt(3)));
+    assert_eq!(String::from_utf8(out).unwrap(), "bc\u{b}1000\n");
+}
+
+#[test]
+fn channels_connect_machines_across_threads() {
+    let (to_machine, input) = mpsc::channel();
+    let (output, from_machine) = mpsc::channel();
+    let handle = thread::spawn(move || successor().run_with(&mut Channel { input, output }));
+    for value in &[1, 2, 3] {
+        to_machine.send(*value).unwrap();
+        assert_eq!(from_machine.recv(), Ok(value + 1));
+    }
+    drop(to_machine);
+    assert_eq!(handle.join().unwrap(), Ok(Output::NeedsInput));
+}
+
+#[test]
+fn closures() {
+    let mut inputs = vec![0, 4];
+    let mut outputs = vec![];
+    let mut device = Closures::new(|| inputs.pop(), |v| outputs.push(v));
+    assert_eq!(successor().run_with(&mut device), Ok(Output::Halt(3)));
+    assert_eq!(outputs, [5]);
+}
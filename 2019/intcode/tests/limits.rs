@@ -0,0 +1,62 @@
+use intcode::cached::CachedMachine;
+use intcode::{IntcodeError, Limit, Limits, Machine, Output};
+use std::time::{Duration, Instant};
+
+/// Adds 1 to address 20 forever.
+const COUNTER: [i64; 7] = [1001, 20, 1, 20, 1105, 1, 0];
+
+fn exhausted(ip: usize, opcode: i64, limit: Limit) -> Result<Output, IntcodeError> {
+    Err(IntcodeError::BudgetExhausted { ip, opcode, limit })
+}
+
+#[test]
+fn instruction_budget_stops_and_resumes() {
+    let mut machine = Machine::new(&COUNTER);
+    machine.limits.instructions = Some(1000);
+    assert_eq!(machine.run(), exhausted(0, 1001, Limit::Instructions));
+    assert_eq!((machine.executed, machine.get(20)), (1000, 500));
+
+    machine.limits.instructions = Some(1501);
+    assert_eq!(machine.run(), exhausted(4, 1105, Limit::Instructions));
+    assert_eq!((machine.executed, machine.get(20)), (1501, 751));
+}
+
+#[test]
+fn memory_limit_stops_before_writing() {
+    let mut machine = Machine::new(&[3, 1 << 40, 4, 1 << 40, 99]);
+    machine.limits.memory = Some(1 << 20);
+    machine.input.push_back(7);
+    assert_eq!(machine.run(), exhausted(0, 3, Limit::Memory));
+    assert_eq!(machine.input, [7]);
+
+    machine.limits.memory = None;
+    machine.memory = Machine::new(&[3, 1 << 10, 4, 1 << 10, 99]).memory;
+    assert_eq!(machine.run(), Ok(Output::Value(7)));
+}
+
+#[test]
+fn deadline_stops_a_runaway_program() {
+    let mut machine = Machine::new(&COUNTER);
+    machine.limits = Limits {
+        deadline: Some(Instant::now() + Duration::from_millis(20)),
+        ..Limits::default()
+    };
+    assert_eq!(machine.run(), exhausted(0, 1001, Limit::Deadline));
+    assert!(machine.executed > 0);
+}
+
+#[test]
+fn cached_machines_stop_at_the_same_limits() {
+    let mut machine = Machine::new(&COUNTER);
+    machine.limits.instructions = Some(1000);
+    let mut cached = CachedMachine::from(machine);
+    assert_eq!(cached.run(), exhausted(0, 1001, Limit::Instructions));
+    assert_eq!((cached.machine().executed, cached.get(20)), (1000, 500));
+
+    let mut machine = Machine::new(&[3, 1 << 40, 99]);
+    machine.limits.memory = Some(1 << 20);
+    machine.input.push_back(7);
+    let mut cached = CachedMachine::from(machine);
+    assert_eq!(cached.run(), exhausted(0, 3, Limit::Memory));
+    assert_eq!(cached.input(), &[7]);
+}
@@ -0,0 +1,46 @@
+use intcode::{Machine, Memory, Output, PagedMemory, VecMemory};
+use std::collections::BTreeMap;
+
+/// Writes input to a far address, reads it back and outputs it.
+const FAR: [i64; 7] = [3, 1 << 40, 4, 1 << 40, 99, 0, 0];
+
+fn cells<M: Memory<Word = i64>>(memory: &mut M) -> Vec<i64> {
+    for (address, value) in &[(3, 30), (1023, 1), (1024, 2), (5000, 5), (1 << 30, 7)] {
+        memory.set(*address, *value);
+    }
+    [0, 1, 3, 1023, 1024, 2047, 5000, 1 << 30, 1 << 40]
+        .iter()
+        .map(|&address| memory.get(address))
+        .collect()
+}
+
+#[test]
+fn backends_agree() {
+    let program = [10, 11, 12, 13];
+    let expected = [10, 11, 30, 1, 2, 0, 5, 7, 0];
+    assert_eq!(cells(&mut PagedMemory::load(&program)), expected);
+    assert_eq!(cells(&mut BTreeMap::load(&program)), expected);
+
+    let mut dense = VecMemory::load(&program);
+    dense.set(5000, 5);
+    assert_eq!((dense.get(1), dense.get(4999), dense.get(5000)), (11, 0, 5));
+}
+
+#[test]
+fn paged_memory_handles_far_addresses() {
+    let mut machine = Machine::with_memory(PagedMemory::load(&FAR));
+    machine.input.push_back(42);
+    assert_eq!(machine.run(), Ok(Output::Value(42)));
+    assert_eq!(machine.run(), Ok(Output::Halt(3)));
+    assert_eq!(machine.get(1 << 40), 42);
+}
+
+#[test]
+fn paged_clones_do_not_share_writes() {
+    let mut original = PagedMemory::<i64>::load(&[1, 2, 3]);
+    let mut copy = original.clone();
+    copy.set(1, 20);
+    original.set(2, 30);
+    assert_eq!((original.get(1), original.get(2)), (2, 30));
+    assert_eq!((copy.get(1), copy.get(2)), (20, 3));
+}
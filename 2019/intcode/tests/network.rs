@@ -0,0 +1,99 @@
+use intcode::network::{self, BusReport, Outcome};
+use intcode::{asm, IntcodeError, Machine};
+
+/// The day 7 examples: a program and the phase settings giving its answer.
+const CHAIN: &[i64] = &[
+    3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+];
+const RING: &[i64] = &[
+    3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28, 1005,
+    28, 6, 99, 0, 0, 5,
+];
+
+/// A bus host. Host 0 starts by sending `(5, 0)` to host 1; every host
+/// passes each packet it gets on to the NAT with `y` counted up to 3.
+const HOST: &str = "
+    in [address]
+    jnz [address], #loop
+    out #1
+    out #5
+    out #0
+loop:
+    in [x]
+    eq [x], #-1, [t]
+    jnz [t], #loop
+    in [y]
+    lt [y], #3, [t]
+    add [y], [t], [y]
+    out #255
+    out [x]
+    out [y]
+    jmp loop
+address: db 0
+x: db 0
+y: db 0
+t: db 0
+";
+
+fn amplifiers(program: &[i64], phases: &[i64]) -> Vec<Machine> {
+    phases
+        .iter()
+        .map(|&phase| Machine::with_input(program, phase))
+        .collect()
+}
+
+#[test]
+fn chain_passes_output_along() {
+    let report = network::chain(amplifiers(CHAIN, &[4, 3, 2, 1, 0]), &[0]).unwrap();
+    assert_eq!(report.outcome, Outcome::Halted);
+    assert_eq!(report.output, [43210]);
+}
+
+#[test]
+fn ring_feeds_output_back() {
+    let report = network::ring(amplifiers(RING, &[9, 8, 7, 6, 5]), &[0]).unwrap();
+    assert_eq!(report.outcome, Outcome::Halted);
+    assert_eq!(report.output.last(), Some(&139629729));
+    assert_eq!(report.output.len(), 5);
+}
+
+#[test]
+fn starved_pipelines_are_quiescent() {
+    // Two machines that each read two values but only get one.
+    let machines = amplifiers(&[3, 9, 3, 9, 4, 9, 99, 0, 0, 0], &[1, 2]);
+    let report = network::ring(machines, &[]).unwrap();
+    assert_eq!(report.outcome, Outcome::Quiescent);
+    assert!(report.output.is_empty());
+}
+
+#[test]
+fn bus_wakes_the_network_through_the_nat() {
+    let host = asm::assemble(HOST).unwrap();
+    let machines = (0..4).map(|_| Machine::new(&host)).collect();
+    let report = network::bus(machines).unwrap();
+    let packets = vec![(5, 1), (5, 2), (5, 3), (5, 3)];
+    assert_eq!(
+        report,
+        BusReport {
+            outcome: Outcome::Quiescent,
+            received: packets.clone(),
+            forwarded: packets,
+        }
+    );
+}
+
+#[test]
+fn machine_errors_stop_the_network() {
+    let machines = vec![
+        Machine::new(&[3, 9, 4, 9, 1105, 1, 0]),
+        Machine::new(&[3, 9, 42]),
+    ];
+    assert_eq!(
+        network::ring(machines.clone(), &[1]),
+        Err(IntcodeError::InvalidOpcode { ip: 2, opcode: 42 })
+    );
+    assert_eq!(
+        network::chain(machines, &[1]),
+        Err(IntcodeError::InvalidOpcode { ip: 2, opcode: 42 })
+    );
+}
@@ -0,0 +1,53 @@
+use intcode::profile::Profile;
+use intcode::{Machine, Opcode, Output};
+
+/// Counts its input down to zero, then outputs it.
+const COUNTDOWN: &[i64] = &[
+    3, 12, // in [12]
+    1001, 12, -1, 12, // add [12], #-1, [12]
+    1005, 12, 2, // jnz [12], #2
+    4, 12, // out [12]
+    99, 0,
+];
+
+#[test]
+fn counts_each_instruction() {
+    let mut machine = Machine::with_input(COUNTDOWN, 3);
+    let mut profile = Profile::new();
+    assert_eq!(profile.run(&mut machine), Ok(Output::Value(0)));
+    assert_eq!(profile.run(&mut machine), Ok(Output::Halt(3)));
+    assert_eq!(profile.instructions, 9);
+    assert_eq!((profile.inputs, profile.outputs), (1, 1));
+    assert_eq!(profile.by_address[..10], [1, 0, 3, 0, 0, 0, 3, 0, 0, 1]);
+    assert_eq!(profile.by_opcode[Opcode::Add as usize], 3);
+    assert_eq!(profile.by_opcode[Opcode::Jnz as usize], 3);
+    assert_eq!(profile.by_opcode[Opcode::Hlt as usize], 1);
+    assert_eq!(profile.touched.iter().collect::<Vec<_>>(), [&12]);
+}
+
+#[test]
+fn waiting_for_input_is_not_counted() {
+    let mut machine = Machine::new(COUNTDOWN);
+    let mut profile = Profile::new();
+    assert_eq!(profile.run(&mut machine), Ok(Output::NeedsInput));
+    assert_eq!(profile.instructions, 0);
+    machine.input.push_back(1);
+    assert_eq!(profile.run(&mut machine), Ok(Output::Value(0)));
+    assert_eq!(profile.instructions, 4);
+}
+
+#[test]
+fn report_lists_the_hot_loop_first() {
+    let mut machine = Machine::with_input(COUNTDOWN, 100);
+    let mut profile = Profile::new();
+    profile.run(&mut machine).unwrap();
+    let report = profile.report(|a| machine.get(a), 2);
+    assert!(report.starts_with("instructions  202\n"));
+    let hot = report
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("address"))
+        .skip(1)
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(hot, ["2", "6"]);
+}
@@ -0,0 +1,56 @@
+//! The shared machine against the answers the days' own machines gave for
+//! the puzzle inputs.
+
+use intcode::{Machine, Output};
+
+const DAY_02: &str = include_str!("../../02/input");
+const DAY_05: &str = include_str!("../../05/input");
+const DAY_09: &str = include_str!("../../09/input");
+
+fn load(source: &str) -> Vec<i64> {
+    intcode::read_program(source.as_bytes()).unwrap()
+}
+
+fn run(source: &str, input: i64) -> Vec<i64> {
+    let mut machine = Machine::with_input(&load(source), input);
+    let mut outputs = vec![];
+    loop {
+        match machine.run() {
+            Ok(Output::Value(v)) => outputs.push(v),
+            Ok(Output::Halt(_)) => return outputs,
+            other => panic!("{:?} at ip {}", other, machine.ip),
+        }
+    }
+}
+
+#[test]
+fn day02_gravity_assist() {
+    let mut machine = Machine::new(&load(DAY_02));
+    machine.set(1, 12);
+    machine.set(2, 2);
+    assert_eq!(machine.run(), Ok(Output::Halt(11590668)));
+}
+
+#[test]
+fn day05_diagnostics() {
+    let mut output = run(DAY_05, 1);
+    assert_eq!(output.pop(), Some(11933517));
+    assert!(output.iter().all(|&v| v == 0), "failed tests: {:?}", output);
+    assert_eq!(run(DAY_05, 5), [10428568]);
+}
+
+#[test]
+fn day09_boost() {
+    assert_eq!(run(DAY_09, 1), [2932210790]);
+    assert_eq!(run(DAY_09, 2), [73144]);
+}
+
+#[test]
+fn programs_span_lines() {
+    assert_eq!(
+        intcode::read_program("1,0,\n0, 0\n\n99".as_bytes()).unwrap(),
+        [1, 0, 0, 0, 99]
+    );
+    let error = intcode::read_program("1,0,0,0\n9x".as_bytes()).unwrap_err();
+    assert_eq!(error.to_string(), "line 2: expected a number, found `9x`");
+}
@@ -0,0 +1,47 @@
+use intcode::{IntcodeError, Machine, Output};
+
+/// Outputs each of three inputs twice, counting them at address 30.
+fn program() -> Vec<i64> {
+    let mut program = vec![
+        3, 31, // in [31]
+        4, 31, // out [31]
+        4, 31, // out [31]
+        1001, 30, 1, 30, // add [30], #1, [30]
+        1007, 30, 3, 32, // lt [30], #3, [32]
+        1005, 32, 0, // jnz [32], #0
+        99,
+    ];
+    program.resize(33, 0);
+    program
+}
+
+#[test]
+fn input_is_read_from_the_front_of_the_queue() {
+    let mut machine = Machine::new(&program());
+    machine.input.extend(&[1, 2, 3]);
+    assert_eq!(machine.run_to_halt(), Ok(vec![1, 1, 2, 2, 3, 3]));
+    assert!(machine.input.is_empty());
+}
+
+#[test]
+fn run_until_output_count_stops_early() {
+    let mut machine = Machine::new(&program());
+    machine.input.extend(&[1, 2]);
+    assert_eq!(machine.run_until_output_count(3), Ok(vec![1, 1, 2]));
+    assert_eq!(machine.run_until_output_count(3), Ok(vec![2]));
+    assert!(!machine.is_halted());
+
+    machine.input.push_back(3);
+    assert_eq!(machine.run_until_output_count(3), Ok(vec![3, 3]));
+    assert!(machine.is_halted());
+    assert_eq!(machine.run(), Ok(Output::Halt(3)));
+}
+
+#[test]
+fn run_to_halt_reports_missing_input() {
+    let mut machine = Machine::with_input(&program(), 7);
+    assert_eq!(
+        machine.run_to_halt(),
+        Err(IntcodeError::MissingInput { ip: 0, opcode: 3 })
+    );
+}
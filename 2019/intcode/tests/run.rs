@@ -0,0 +1,150 @@
+//! `intcode-run`'s exit codes, as listed in its help.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Reads a value and outputs it twice.
+const ECHO: &str = "3,9,4,9,4,9,99,0,0,0";
+
+/// Reads two values and outputs them in order.
+const PAIR: &str = "3,11,3,12,4,11,4,12,99,0,0,0,0";
+
+/// Loops forever.
+const SPIN: &str = "1105,1,0";
+
+fn run(name: &str, program: &str, args: &[&str]) -> Output {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, program).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_intcode-run"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn halted() {
+    let output = run("run-halted.ic", ECHO, &["--input", "7", "--show-halt"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7\n7\n3\n");
+}
+
+#[test]
+fn ascii_output() {
+    let output = run(
+        "run-ascii.ic",
+        ECHO,
+        &["--input", "65", "--output", "ascii"],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "AA");
+}
+
+#[test]
+fn bad_arguments_and_files() {
+    for &(name, program, args) in &[
+        ("run-malformed.ic", "3,9,4,x", &[][..]),
+        ("run-bad-input.ic", ECHO, &["--input", "seven"][..]),
+        ("run-bad-poke.ic", ECHO, &["--poke", "9"][..]),
+        (
+            "run-poke-limit.ic",
+            ECHO,
+            &["--max-memory", "64", "--poke", "64=1"][..],
+        ),
+        ("run-bad-timeout.ic", SPIN, &["--timeout=-1"][..]),
+        ("run-huge-timeout.ic", SPIN, &["--timeout", "1e19"][..]),
+        ("run-long-timeout.ic", SPIN, &["--timeout", "1e300"][..]),
+        (
+            "run-missing-input.ic",
+            ECHO,
+            &["--ascii-input", "no/such/input.txt"][..],
+        ),
+    ] {
+        let output = run(name, program, args);
+        assert_eq!(output.status.code(), Some(1), "{}", name);
+        assert!(!stderr(&output).contains("panicked"), "{}", name);
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_intcode-run"))
+        .arg("no/such/program.ic")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn input_in_command_line_order() {
+    let text = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("run-order.txt");
+    fs::write(&text, "A").unwrap();
+    let text = text.to_str().unwrap();
+    let output = run(
+        "run-order.ic",
+        PAIR,
+        &["--ascii-input", text, "--input", "7"],
+    );
+    assert_eq!(stdout(&output), "65\n7\n");
+    let output = run(
+        "run-order.ic",
+        PAIR,
+        &["--input", "7", "--ascii-input", text],
+    );
+    assert_eq!(stdout(&output), "7\n65\n");
+}
+
+#[test]
+fn starved() {
+    let output = run("run-starved.ic", ECHO, &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "program needs more input at ip 0\n");
+}
+
+#[test]
+fn faults() {
+    let output = run("run-fault.ic", "42", &[]);
+    assert_eq!(output.status.code(), Some(3));
+
+    let output = run(
+        "run-not-ascii.ic",
+        ECHO,
+        &["--input", "1000", "--output", "ascii"],
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stderr(&output), "output 1000 is not a character\n");
+}
+
+#[test]
+fn limits() {
+    let output = run(
+        "run-instructions.ic",
+        ECHO,
+        &["--input", "7", "--max-instructions", "2"],
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(stdout(&output), "7\n");
+
+    let output = run(
+        "run-memory.ic",
+        ECHO,
+        &["--input", "7", "--max-memory", "9"],
+    );
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn timeout() {
+    let output = run("run-timeout.ic", SPIN, &["--timeout", "0.05"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(
+        stderr(&output),
+        "time budget exhausted before opcode 1105 at ip 0\n"
+    );
+}
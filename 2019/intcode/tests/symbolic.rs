@@ -0,0 +1,147 @@
+use intcode::symbolic::{self, Method, Poly, SymbolicError, SymbolicMachine};
+use intcode::{IntcodeError, Machine, Output};
+use std::ops::RangeInclusive;
+
+const DAY_02: &str = include_str!("../../02/input");
+
+#[test]
+fn day_02_is_linear_in_noun_and_verb() {
+    let program = intcode::read_program(DAY_02.as_bytes()).unwrap();
+    let mut machine = SymbolicMachine::new(&program, &[1, 2]);
+    let poly = match machine.run().unwrap() {
+        Output::Halt(poly) => poly,
+        output => panic!("{:?}", output),
+    };
+    assert_eq!(poly.cells().into_iter().collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(poly.eval(|a| [0, 12, 2][a]), 11590668);
+
+    let cells = [(1, 0..=99), (2, 0..=99)];
+    assert_eq!(symbolic::solve(&poly, 19690720, &cells), Some(vec![22, 54]));
+    let (values, method) = symbolic::search(&program, &cells, 19690720).unwrap();
+    assert_eq!(values, [22, 54]);
+    assert_eq!(method, Method::Symbolic(poly));
+}
+
+#[test]
+fn polynomials() {
+    let x = Poly::cell(1);
+    let y = Poly::cell(2);
+    let poly = (x.clone() + Poly::constant(3)) * (y + Poly::constant(-2)) + x * Poly::constant(2);
+    assert_eq!(poly.to_string(), "[1] * [2] + 3 * [2] - 6");
+    assert_eq!(poly.eval(|a| a as i64 * 5), 5 * 10 + 3 * 10 - 6);
+    assert_eq!(Poly::constant(7).as_constant(), Some(7));
+    assert_eq!(poly.as_constant(), None);
+}
+
+#[test]
+fn symbolic_branch_falls_back_to_brute_force() {
+    // [0] = if [20] < 5 { [21] * 3 } else { [21] + 100 }
+    let program = [
+        1007, 20, 5, 22, // lt [20], #5, [22]
+        1005, 22, 12, // jnz [22], #12
+        1001, 21, 100, 0,  // add [21], #100, [0]
+        99, // hlt
+        1002, 21, 3, 0,  // mul [21], #3, [0]
+        99, // hlt
+        0, 0, 0, 0, 0, 0,
+    ];
+    let cells = [(20, 0..=9), (21, 0..=50)];
+    let (values, method) = symbolic::search(&program, &cells, 120).unwrap();
+    assert_eq!(values, [0, 40]);
+    assert_eq!(
+        method,
+        Method::BruteForce(SymbolicError::SymbolicBranch { ip: 4 })
+    );
+    assert_eq!(
+        symbolic::brute_force(&program, &cells, 145),
+        Some(vec![5, 45])
+    );
+    assert_eq!(symbolic::search(&program, &cells, 1), None);
+}
+
+#[test]
+fn solutions_may_wrap_around() {
+    let times = |c: i64| Poly::cell(1) * Poly::constant(c);
+    let all = [(1, i64::MIN..=i64::MAX)];
+    let inverse = symbolic::solve(&times(3), 1, &all).unwrap()[0];
+    assert_eq!(inverse.wrapping_mul(3), 1);
+
+    // 4 * x == 8 for x == 2 modulo 2^62, smallest first.
+    assert_eq!(
+        symbolic::solve(&times(4), 8, &all),
+        Some(vec![i64::MIN + 2])
+    );
+    assert_eq!(symbolic::solve(&times(4), 8, &[(1, 0..=10)]), Some(vec![2]));
+    assert_eq!(symbolic::solve(&times(4), 6, &all), None);
+    assert_eq!(
+        symbolic::solve(&times(1 << 62), 0, &[(1, 1..=10)]),
+        Some(vec![4])
+    );
+}
+
+#[test]
+fn search_finds_wrapped_solutions() {
+    // [0] = [5] * 2^62
+    let program = [1002, 5, 1 << 62, 0, 99, 0];
+    let (values, method) = symbolic::search(&program, &[(5, 1..=10)], 0).unwrap();
+    assert_eq!(values, [4]);
+    assert!(matches!(method, Method::Symbolic(_)));
+    assert_eq!(symbolic::search(&program, &[(5, 1..=3)], 0), None);
+}
+
+#[test]
+fn faulting_solutions_fall_back_to_brute_force() {
+    // [0] = [21], after reading through the address at [1], which faults
+    // when negative.
+    let mut program = vec![
+        1001, 0, 0, 24, // add [x], #0, [24], x being the symbol at 1
+        1001, 21, 0, 0, // add [21], #0, [0]
+        99,
+    ];
+    program.resize(25, 0);
+    let cells = [(1, -1..=5), (21, 0..=9)];
+    let (values, method) = symbolic::search(&program, &cells, 7).unwrap();
+    assert_eq!(values, [0, 7]);
+    assert_eq!(
+        method,
+        Method::BruteForce(SymbolicError::NoSymbolicSolution)
+    );
+}
+
+#[test]
+fn brute_force_edge_cases() {
+    let program = [1101, 3, 4, 0, 99];
+    assert_eq!(symbolic::brute_force(&program, &[], 7), Some(vec![]));
+    assert_eq!(symbolic::brute_force(&program, &[], 8), None);
+    assert_eq!(
+        symbolic::brute_force(&program, &[(1, RangeInclusive::new(5, 4))], 7),
+        None
+    );
+    assert_eq!(
+        symbolic::brute_force(&program, &[(1, -50..=3)], 7),
+        Some(vec![3])
+    );
+    assert_eq!(
+        symbolic::brute_force(&program, &[(1, -50..=50), (2, 3..=5)], 10),
+        Some(vec![5, 5])
+    );
+}
+
+#[test]
+fn faults_match_the_machine() {
+    for program in &[
+        [109, 5, 11101, 1, 1, 3, 99],   // add writing to an immediate
+        [109, -10, 21101, 1, 1, 0, 99], // add writing to [rb+0] == [-10]
+    ] {
+        let expected = Machine::new(program).run().unwrap_err();
+        let mut machine = SymbolicMachine::new(program, &[]);
+        assert_eq!(machine.run(), Err(SymbolicError::Fault(expected)));
+    }
+    assert!(matches!(
+        SymbolicMachine::new(&[109, 5, 11101, 1, 1, 3, 99], &[]).run(),
+        Err(SymbolicError::Fault(IntcodeError::ImmediateWrite {
+            rb: 5,
+            ..
+        }))
+    ));
+}
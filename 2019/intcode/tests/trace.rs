@@ -0,0 +1,112 @@
+use intcode::trace::{self, Divergence, Event, Recorder};
+use intcode::{Machine, Output};
+use std::io;
+
+/// Reads a value, doubles it and outputs the result.
+const DOUBLE: [i64; 10] = [3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+
+/// Records a run of `DOUBLE` on `input` until it halts or needs input.
+fn record(input: &[i64]) -> Vec<u8> {
+    let mut machine = Machine::new(&DOUBLE);
+    machine.input.extend(input);
+    let mut recorder = Recorder::new(vec![]);
+    while let Output::Value(_) = recorder.run(&mut machine).unwrap() {}
+    recorder.finish().unwrap()
+}
+
+fn events(trace: &[u8]) -> Vec<io::Result<Event>> {
+    trace::read(trace).collect()
+}
+
+#[test]
+fn recorder_writes_one_line_per_instruction() {
+    let mut machine = Machine::with_input(&DOUBLE, 21);
+    let mut recorder = Recorder::new(vec![]);
+    assert_eq!(recorder.run(&mut machine).unwrap(), Output::Value(42));
+    assert_eq!(recorder.run(&mut machine).unwrap(), Output::Halt(3));
+    assert_eq!(recorder.events, 4);
+    let trace = recorder.finish().unwrap();
+    assert_eq!(trace.iter().filter(|&&b| b == b'\n').count(), 4);
+
+    let events = trace::read(&trace[..])
+        .collect::<io::Result<Vec<Event>>>()
+        .unwrap();
+    assert_eq!(events[0].input, Some(21));
+    let write = events[1].write.as_ref().unwrap();
+    assert_eq!((write.address, write.value), (9, 42));
+    assert_eq!(events[2].output, Some(42));
+    assert_eq!(events[3].opcode, 99);
+}
+
+#[test]
+fn replay_of_a_full_recording_matches() {
+    let trace = record(&[21]);
+    assert_eq!(
+        trace::replay(Machine::new(&DOUBLE), events(&trace)).unwrap(),
+        None
+    );
+
+    // A recording that stopped for input ends where the replay stops too.
+    let trace = record(&[]);
+    assert!(trace.is_empty());
+    assert_eq!(
+        trace::replay(Machine::new(&DOUBLE), events(&trace)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn replay_of_a_truncated_recording_diverges() {
+    let trace = record(&[21]);
+    let lines = trace.split(|&b| b == b'\n').collect::<Vec<_>>();
+    // Cutting before the first event is indistinguishable from a recording
+    // that stopped for input, since the inputs come from the trace.
+    for kept in 1..4 {
+        let truncated = lines[..kept].join(&b'\n');
+        assert_eq!(
+            trace::replay(Machine::new(&DOUBLE), events(&truncated)).unwrap(),
+            Some(Divergence::Length {
+                index: kept as u64,
+                left_longer: false,
+            }),
+            "{} events kept",
+            kept
+        );
+    }
+}
+
+#[test]
+fn replay_reports_a_changed_program() {
+    let trace = record(&[21]);
+    let mut program = DOUBLE;
+    program[4] = 3;
+    match trace::replay(Machine::new(&program), events(&trace)).unwrap() {
+        Some(Divergence::Event { index, left, right }) => {
+            assert_eq!(index, 1);
+            assert_eq!(left.write.unwrap().value, 42);
+            assert_eq!(right.write.unwrap().value, 63);
+        }
+        divergence => panic!("{:?}", divergence),
+    }
+}
+
+#[test]
+fn diff_finds_the_first_difference() {
+    let (a, b) = (record(&[21]), record(&[22]));
+    assert_eq!(trace::diff(events(&a), events(&a)).unwrap(), None);
+    match trace::diff(events(&a), events(&b)).unwrap() {
+        Some(Divergence::Event { index, left, right }) => {
+            assert_eq!(index, 0);
+            assert_eq!((left.input, right.input), (Some(21), Some(22)));
+        }
+        divergence => panic!("{:?}", divergence),
+    }
+    assert_eq!(
+        trace::diff(events(&a), events(&record(&[]))).unwrap(),
+        Some(Divergence::Length {
+            index: 0,
+            left_longer: true,
+        })
+    );
+    assert!(trace::diff(events(b"not json\n"), events(&a)).is_err());
+}
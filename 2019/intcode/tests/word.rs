@@ -0,0 +1,77 @@
+use intcode::{IntcodeError, Machine, Memory, Output, PagedMemory, VecMemory, Word};
+use num::BigInt;
+
+/// Squares its input twice and outputs the result.
+const SQUARE_TWICE: [i64; 14] = [
+    3, 13, // in [13]
+    2, 13, 13, 13, // mul [13], [13], [13]
+    2, 13, 13, 13, // mul [13], [13], [13]
+    4, 13, // out [13]
+    99, 0,
+];
+
+fn machine<M: Memory>(input: i64) -> Machine<M> {
+    let program = SQUARE_TWICE
+        .iter()
+        .map(|&w| M::Word::from_i64(w))
+        .collect::<Vec<_>>();
+    let mut machine = Machine::with_memory(M::load(&program));
+    machine.input.push_back(M::Word::from_i64(input));
+    machine
+}
+
+#[test]
+fn words_agree_while_values_fit() {
+    assert_eq!(
+        machine::<VecMemory>(1000).run(),
+        Ok(Output::Value(1_000_000_000_000))
+    );
+    assert_eq!(
+        machine::<VecMemory<i128>>(1000).run(),
+        Ok(Output::Value(1_000_000_000_000))
+    );
+    assert_eq!(
+        machine::<PagedMemory<BigInt>>(1000).run(),
+        Ok(Output::Value(BigInt::from(1_000_000_000_000i64)))
+    );
+}
+
+#[test]
+fn wider_words_do_not_overflow() {
+    let big = 10i128.pow(20);
+    assert_eq!(
+        machine::<VecMemory>(100_000).run(),
+        Ok(Output::Value(big as i64))
+    );
+    assert_eq!(
+        machine::<VecMemory<i128>>(100_000).run(),
+        Ok(Output::Value(big))
+    );
+    assert_eq!(
+        machine::<VecMemory<BigInt>>(100_000).run(),
+        Ok(Output::Value(BigInt::from(big)))
+    );
+}
+
+#[test]
+fn checked_arithmetic_reports_overflow() {
+    let mut narrow = machine::<VecMemory>(100_000);
+    narrow.checked = true;
+    assert_eq!(
+        narrow.run(),
+        Err(IntcodeError::Overflow { ip: 6, opcode: 2 })
+    );
+
+    let mut wide = machine::<VecMemory<i128>>(100_000);
+    wide.checked = true;
+    assert_eq!(wide.run(), Ok(Output::Value(10i128.pow(20))));
+}
+
+#[test]
+fn addresses_must_fit_in_an_i64() {
+    let mut machine = Machine::with_memory(VecMemory::<i128>::load(&[4, 1 << 70, 99]));
+    assert_eq!(
+        machine.run(),
+        Err(IntcodeError::Overflow { ip: 0, opcode: 4 })
+    );
+}